@@ -0,0 +1,6 @@
+-- In-memory AppState persisted on shutdown and restored on the next boot
+CREATE TABLE IF NOT EXISTS app_snapshots (
+    id TEXT PRIMARY KEY,
+    state JSONB NOT NULL,
+    saved_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
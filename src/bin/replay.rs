@@ -0,0 +1,147 @@
+//! Replays a JSONL file of recorded requests (see `shuttlings_cch24::replay`)
+//! and reports which responses matched.
+//!
+//! ```sh
+//! cargo run --features replay --bin replay -- tests/fixtures/routes.jsonl --target https://my-deploy.example
+//! cargo run --features replay --bin replay -- tests/fixtures/routes.jsonl   # in-process router
+//! ```
+//!
+//! Without `--target` the requests go to an in-process router backed by
+//! `DATABASE_URL`, or by a pool that never connects when it is unset.
+
+use std::{process::ExitCode, sync::Arc};
+
+use anyhow::Context;
+use axum::{body::Body, http::Request, Router};
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use hyper_util::{
+    client::legacy::{connect::HttpConnector, Client},
+    rt::TokioExecutor,
+};
+use shuttlings_cch24::{
+    config::ServerConfig,
+    replay::{self, RecordedResponse},
+    router, AppState,
+};
+use sqlx::postgres::PgPoolOptions;
+use tower::ServiceExt;
+
+enum Target {
+    Remote {
+        base: String,
+        client: Client<HttpsConnector<HttpConnector>, Body>,
+    },
+    InProcess(Router),
+}
+
+impl Target {
+    async fn send(&self, request: Request<Body>) -> anyhow::Result<RecordedResponse> {
+        match self {
+            Target::Remote { client, .. } => {
+                RecordedResponse::record(client.request(request).await?).await
+            }
+            Target::InProcess(router) => {
+                RecordedResponse::record(router.clone().oneshot(request).await?).await
+            }
+        }
+    }
+
+    fn base(&self) -> &str {
+        match self {
+            Target::Remote { base, .. } => base,
+            Target::InProcess(_) => "",
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<ExitCode> {
+    let mut args = std::env::args().skip(1);
+    let mut fixtures_path = None;
+    let mut target_url = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--target" => target_url = Some(args.next().context("--target needs a url")?),
+            _ => fixtures_path = Some(arg),
+        }
+    }
+    let fixtures_path =
+        fixtures_path.context("usage: replay <fixtures.jsonl> [--target http(s)://host:port]")?;
+    let fixtures = replay::load(&fixtures_path)?;
+
+    let target = match target_url {
+        Some(base) => Target::Remote {
+            base,
+            client: Client::builder(TokioExecutor::new()).build(
+                HttpsConnectorBuilder::new()
+                    .with_webpki_roots()
+                    .https_or_http()
+                    .enable_http1()
+                    .build(),
+            ),
+        },
+        None => {
+            let config = ServerConfig::load(None)?;
+            let pool = match &config.database_url {
+                Some(url) => PgPoolOptions::new().connect(url).await?,
+                None => PgPoolOptions::new().connect_lazy("postgres://localhost/cch24_unused")?,
+            };
+            Target::InProcess(router(Arc::new(AppState::new(pool, &config))))
+        }
+    };
+
+    let name_width = fixtures
+        .iter()
+        .map(|fixture| fixture.name.len())
+        .max()
+        .unwrap_or(0)
+        .max("NAME".len());
+    println!("{:<6} {:<name_width$} STATUS", "RESULT", "NAME");
+
+    let mut failed = 0;
+    for fixture in &fixtures {
+        let request = fixture.to_request(target.base())?;
+        let (status, mismatches) = match target.send(request).await {
+            Ok(response) => (
+                response.status.as_u16().to_string(),
+                fixture
+                    .check(&response)
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect(),
+            ),
+            Err(error) => (
+                String::from("-"),
+                vec![format!("request failed: {error:#}")],
+            ),
+        };
+
+        let result = if mismatches.is_empty() {
+            "PASS"
+        } else {
+            "FAIL"
+        };
+        println!("{result:<6} {:<name_width$} {status}", fixture.name);
+        for mismatch in &mismatches {
+            for line in mismatch.lines() {
+                println!("       {line}");
+            }
+        }
+        if !mismatches.is_empty() {
+            failed += 1;
+        }
+    }
+
+    println!(
+        "\n{} passed, {} failed, {} total",
+        fixtures.len() - failed,
+        failed,
+        fixtures.len()
+    );
+
+    Ok(if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
@@ -0,0 +1,119 @@
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use anyhow::Context;
+use shuttlings_cch24::{config::ServerConfig, router, telemetry, AppState, MIGRATOR};
+use sqlx::postgres::PgPoolOptions;
+use tokio::{net::TcpListener, signal, sync::Notify};
+
+/// How long dropping the aborted connection tasks may take before the
+/// snapshot is taken anyway.
+const SERVER_STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let config_path = std::env::args()
+        .nth(1)
+        .or_else(|| std::env::var("CCH24_CONFIG").ok())
+        .map(PathBuf::from);
+    let config = ServerConfig::load(config_path.as_deref())?;
+    telemetry::init(&config.log)?;
+
+    let database_url = config
+        .database_url
+        .as_deref()
+        .context("no database_url configured (set it in the config file or DATABASE_URL)")?;
+    let pool = PgPoolOptions::new()
+        .connect(database_url)
+        .await
+        .context("connecting to postgres")?;
+
+    MIGRATOR.run(&pool).await.context("running migrations")?;
+
+    let app_state = Arc::new(
+        AppState::restore(pool, &config)
+            .await
+            .context("restoring state snapshot")?,
+    );
+
+    let listener = TcpListener::bind(config.bind_address)
+        .await
+        .with_context(|| format!("binding {}", config.bind_address))?;
+    tracing::info!(address = %config.bind_address, "listening");
+
+    // Connections run on their own runtime so that, if draining times out,
+    // shutting it down drops every connection task before the snapshot is
+    // taken. Pooled connections opened by handlers belong to that runtime
+    // too, so the snapshot goes through a fresh pool afterwards.
+    let server_runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .thread_name("cch24-server")
+        .build()
+        .context("building server runtime")?;
+
+    let stop_accepting = Arc::new(Notify::new());
+    let server = axum::serve(listener, router(app_state.clone())).with_graceful_shutdown({
+        let stop_accepting = stop_accepting.clone();
+        async move { stop_accepting.notified().await }
+    });
+    let mut server = server_runtime.spawn(async move { server.await });
+
+    tokio::select! {
+        result = &mut server => result??,
+        () = shutdown_signal() => {
+            tracing::info!(
+                timeout_secs = config.shutdown_timeout_secs,
+                "shutdown requested, draining in-flight requests"
+            );
+            stop_accepting.notify_one();
+
+            match tokio::time::timeout(config.shutdown_timeout(), &mut server).await {
+                Ok(result) => result??,
+                Err(_) => {
+                    tracing::warn!("in-flight requests did not finish in time, aborting them");
+                }
+            }
+        }
+    }
+
+    // Waits for the remaining connection tasks to be dropped, so no handler
+    // can touch the state while it is persisted.
+    tokio::task::spawn_blocking(move || server_runtime.shutdown_timeout(SERVER_STOP_TIMEOUT))
+        .await
+        .context("stopping server runtime")?;
+
+    let snapshot_pool = PgPoolOptions::new()
+        .max_connections(1)
+        .connect(database_url)
+        .await
+        .context("reconnecting to postgres")?;
+    app_state
+        .persist(&snapshot_pool)
+        .await
+        .context("persisting state snapshot")?;
+
+    Ok(())
+}
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = ctrl_c => {},
+        () = terminate => {},
+    }
+}
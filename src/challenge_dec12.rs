@@ -1,15 +1,20 @@
 use std::{fmt::Display, sync::Arc, usize};
 
 use axum::{
-    extract::{Path, State},
+    extract::State,
     http::StatusCode,
     response::{IntoResponse, Response},
 };
 use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
 
-use crate::AppState;
+use crate::{
+    error::{AppError, ProblemDetails},
+    extract::Path,
+    AppState,
+};
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
 enum BoardTile {
     Wall,
     Blank,
@@ -34,6 +39,27 @@ pub struct MilkCookieGame {
     board_state: Vec<Vec<BoardTile>>,
     size: usize,
     rng: rand::rngs::StdRng,
+    random_boards_drawn: u64,
+}
+
+/// Serializable form of [`MilkCookieGame`]. The rng is not stored directly;
+/// it is rebuilt from the seed by replaying `random_boards_drawn` boards.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MilkCookieGameSnapshot {
+    board_state: Vec<Vec<BoardTile>>,
+    size: usize,
+    random_boards_drawn: u64,
+}
+
+/// Most boards [`MilkCookieGame::restore`] replays. A snapshot claiming more
+/// is treated as corrupt and the rng starts over from the seed, so a bad
+/// count cannot stall startup.
+const MAX_REPLAYED_BOARDS: u64 = 100_000;
+
+impl MilkCookieGameSnapshot {
+    pub fn size(&self) -> usize {
+        self.size
+    }
 }
 
 impl MilkCookieGame {
@@ -42,12 +68,39 @@ impl MilkCookieGame {
             board_state: vec![Vec::with_capacity(size); size],
             size,
             rng: rand::rngs::StdRng::seed_from_u64(2024),
+            random_boards_drawn: 0,
+        }
+    }
+
+    pub fn snapshot(&self) -> MilkCookieGameSnapshot {
+        MilkCookieGameSnapshot {
+            board_state: self.board_state.clone(),
+            size: self.size,
+            random_boards_drawn: self.random_boards_drawn,
+        }
+    }
+
+    pub fn restore(snapshot: MilkCookieGameSnapshot) -> Self {
+        let mut game = MilkCookieGame::new(snapshot.size);
+        if snapshot.random_boards_drawn <= MAX_REPLAYED_BOARDS {
+            for _ in 0..snapshot.random_boards_drawn {
+                game.make_seeded_random();
+            }
+        } else {
+            tracing::warn!(
+                random_boards_drawn = snapshot.random_boards_drawn,
+                max = MAX_REPLAYED_BOARDS,
+                "too many random boards to replay, reseeding the rng"
+            );
         }
+        game.board_state = snapshot.board_state;
+        game
     }
 
     fn reset(&mut self) {
         self.board_state = vec![Vec::with_capacity(self.size); self.size];
         self.rng = rand::rngs::StdRng::seed_from_u64(2024);
+        self.random_boards_drawn = 0;
     }
 
     fn place(&mut self, tile: BoardTile, column: usize) {
@@ -179,6 +232,7 @@ impl MilkCookieGame {
     }
 
     fn make_seeded_random(&mut self) {
+        self.random_boards_drawn += 1;
         self.board_state = vec![vec![BoardTile::Blank; self.size]; self.size];
         for row in (0..self.size).rev() {
             for column in 0..self.size {
@@ -199,15 +253,41 @@ impl MilkCookieGame {
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/12/board",
+    tag = "dec12",
+    responses((status = 200, description = "Current board", body = String, content_type = "text/plain")),
+)]
 pub async fn milk_cookie_game_state(State(state): State<Arc<AppState>>) -> impl IntoResponse {
     state.milk_cookie_game.try_read().unwrap().to_string()
 }
 
+#[utoipa::path(
+    post,
+    path = "/12/reset",
+    tag = "dec12",
+    responses((status = 200, description = "Empty board", body = String, content_type = "text/plain")),
+)]
 pub async fn milk_cookie_game_reset(State(state): State<Arc<AppState>>) -> impl IntoResponse {
     state.milk_cookie_game.try_write().unwrap().reset();
     state.milk_cookie_game.try_read().unwrap().to_string()
 }
 
+#[utoipa::path(
+    post,
+    path = "/12/place/{team}/{column}",
+    tag = "dec12",
+    params(
+        ("team" = String, Path, description = "`cookie` or `milk`"),
+        ("column" = usize, Path, description = "1-based column to drop the tile into"),
+    ),
+    responses(
+        (status = 200, description = "Board after the placement", body = String, content_type = "text/plain"),
+        (status = 400, description = "Unknown team or column off the board", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 503, description = "Game already over", body = String, content_type = "text/plain"),
+    ),
+)]
 pub async fn milk_cookie_game_place(
     State(state): State<Arc<AppState>>,
     Path((team, column)): Path<(String, usize)>,
@@ -229,11 +309,12 @@ pub async fn milk_cookie_game_place(
         ));
     }
 
-    state
-        .milk_cookie_game
-        .try_write()
-        .unwrap()
-        .place(tile_kind, column);
+    let mut game = state.milk_cookie_game.try_write().unwrap();
+    game.place(tile_kind, column);
+    if !game.can_play() {
+        metrics::counter!("milk_cookie_games_won_total", "team" => team).increment(1);
+    }
+    drop(game);
 
     Ok((
         StatusCode::OK,
@@ -241,6 +322,12 @@ pub async fn milk_cookie_game_place(
     ))
 }
 
+#[utoipa::path(
+    get,
+    path = "/12/random-board",
+    tag = "dec12",
+    responses((status = 200, description = "Next board from the seeded rng", body = String, content_type = "text/plain")),
+)]
 pub async fn milk_cookie_not_random(State(state): State<Arc<AppState>>) -> impl IntoResponse {
     state
         .milk_cookie_game
@@ -255,11 +342,20 @@ pub enum Day12AppError {
     BadPlacement,
 }
 
+impl From<Day12AppError> for AppError {
+    fn from(error: Day12AppError) -> Self {
+        match error {
+            Day12AppError::BadPlacement => AppError::new(
+                StatusCode::BAD_REQUEST,
+                "board.bad_placement",
+                "Team must be cookie or milk and the column must be on the board",
+            ),
+        }
+    }
+}
+
 impl IntoResponse for Day12AppError {
     fn into_response(self) -> Response {
-        match self {
-            Day12AppError::BadPlacement => (StatusCode::BAD_REQUEST, ""),
-        }
-        .into_response()
+        AppError::from(self).into_response()
     }
 }
@@ -1,13 +1,17 @@
 use axum::{
     http::{header, HeaderMap, StatusCode},
     response::{IntoResponse, Response},
-    Json,
 };
 use biscotti::{Processor, ProcessorConfig, RequestCookies};
 use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 
+use crate::{
+    error::{AppError, ProblemDetails},
+    extract::Json,
+};
+
 #[derive(Debug, Serialize, Deserialize)]
 struct Claims {
     present: String,
@@ -16,6 +20,13 @@ struct Claims {
 
 const SIGNING_SECRET: &str = "santa";
 
+#[utoipa::path(
+    post,
+    path = "/16/wrap",
+    tag = "dec16",
+    request_body(content = Object, description = "Any JSON present"),
+    responses((status = 200, description = "Present wrapped into the `gift` cookie")),
+)]
 pub async fn wrap_present(present: Json<Value>) -> impl IntoResponse {
     let claims = Claims {
         present: present.to_string(),
@@ -34,6 +45,16 @@ pub async fn wrap_present(present: Json<Value>) -> impl IntoResponse {
     )
 }
 
+#[utoipa::path(
+    get,
+    path = "/16/unwrap",
+    tag = "dec16",
+    params(("gift" = String, Cookie, description = "Token set by /16/wrap")),
+    responses(
+        (status = 200, description = "The wrapped present", body = Object),
+        (status = 400, description = "No gift cookie", body = Object),
+    ),
+)]
 pub async fn unwrap_present(headers: HeaderMap) -> impl IntoResponse {
     let processor: Processor = ProcessorConfig::default().into();
 
@@ -66,6 +87,17 @@ pub async fn unwrap_present(headers: HeaderMap) -> impl IntoResponse {
     (StatusCode::OK, Json(gift))
 }
 
+#[utoipa::path(
+    post,
+    path = "/16/decode",
+    tag = "dec16",
+    request_body(content = String, description = "JWT signed with Santa's RSA key", content_type = "text/plain"),
+    responses(
+        (status = 200, description = "Decoded claims", body = Object),
+        (status = 400, description = "Malformed token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Signature does not match", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+)]
 pub async fn unwrap_encrypted_present(body: String) -> Result<impl IntoResponse, Day16AppError> {
     let mut custom_validation = Validation::default();
     custom_validation.required_spec_claims = [].into();
@@ -84,20 +116,31 @@ pub enum Day16AppError {
     JWTError(jsonwebtoken::errors::Error),
 }
 
-impl IntoResponse for Day16AppError {
-    fn into_response(self) -> Response {
-        match self {
+impl From<Day16AppError> for AppError {
+    fn from(error: Day16AppError) -> Self {
+        match error {
             Day16AppError::JWTError(rejection) => {
-                println!("{}", rejection);
+                tracing::warn!(error = %rejection, "rejected present token");
                 match rejection.kind() {
-                    jsonwebtoken::errors::ErrorKind::InvalidSignature => {
-                        (StatusCode::UNAUTHORIZED, "")
-                    }
-                    _ => (StatusCode::BAD_REQUEST, ""),
+                    jsonwebtoken::errors::ErrorKind::InvalidSignature => AppError::new(
+                        StatusCode::UNAUTHORIZED,
+                        "present.invalid_signature",
+                        "Present signature does not match Santa's key",
+                    ),
+                    _ => AppError::new(
+                        StatusCode::BAD_REQUEST,
+                        "present.invalid_token",
+                        rejection.to_string(),
+                    ),
                 }
             }
         }
-        .into_response()
+    }
+}
+
+impl IntoResponse for Day16AppError {
+    fn into_response(self) -> Response {
+        AppError::from(self).into_response()
     }
 }
 
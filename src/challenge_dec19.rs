@@ -1,10 +1,9 @@
 use std::sync::Arc;
 
 use axum::{
-    extract::{Path, Query, State},
+    extract::State,
     http::StatusCode,
     response::{IntoResponse, Response},
-    Json,
 };
 use rand::Rng;
 use serde::{Deserialize, Serialize};
@@ -15,9 +14,20 @@ use sqlx::{
     },
     FromRow,
 };
+use utoipa::{IntoParams, ToSchema};
 
-use crate::AppState;
+use crate::{
+    error::{AppError, ProblemDetails},
+    extract::{Json, Path, Query},
+    AppState,
+};
 
+#[utoipa::path(
+    post,
+    path = "/19/reset",
+    tag = "dec19",
+    responses((status = 200, description = "All quotes removed")),
+)]
 pub async fn quotes_reset(state: State<Arc<AppState>>) -> impl IntoResponse {
     sqlx::query("TRUNCATE quotes")
         .execute(&state.pool)
@@ -26,14 +36,14 @@ pub async fn quotes_reset(state: State<Arc<AppState>>) -> impl IntoResponse {
     StatusCode::OK
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct QuoteInsert {
     author: String,
     quote: String,
 }
 
-#[derive(FromRow, Serialize)]
-struct Quote {
+#[derive(FromRow, Serialize, ToSchema)]
+pub struct Quote {
     id: Uuid,
     author: String,
     quote: String,
@@ -50,6 +60,13 @@ async fn retrieve_quote_by_id(id: &Uuid, pool: &sqlx::PgPool) -> Result<Quote, D
     Ok(quote)
 }
 
+#[utoipa::path(
+    get,
+    path = "/19/cite/{id}",
+    tag = "dec19",
+    params(("id" = Uuid, Path, description = "Quote id")),
+    responses((status = 200, description = "The quote", body = Quote), (status = 404, description = "Quote not found", body = ProblemDetails, content_type = "application/problem+json")),
+)]
 pub async fn quote_by_id(
     state: State<Arc<AppState>>,
     Path(id): Path<Uuid>,
@@ -58,6 +75,13 @@ pub async fn quote_by_id(
     Ok((StatusCode::OK, Json(quote)))
 }
 
+#[utoipa::path(
+    delete,
+    path = "/19/remove/{id}",
+    tag = "dec19",
+    params(("id" = Uuid, Path, description = "Quote id")),
+    responses((status = 200, description = "The removed quote", body = Quote), (status = 404, description = "Quote not found", body = ProblemDetails, content_type = "application/problem+json")),
+)]
 pub async fn remove_quote_by_id(
     state: State<Arc<AppState>>,
     Path(id): Path<Uuid>,
@@ -71,6 +95,14 @@ pub async fn remove_quote_by_id(
     Ok((StatusCode::OK, Json(quote)))
 }
 
+#[utoipa::path(
+    put,
+    path = "/19/undo/{id}",
+    tag = "dec19",
+    params(("id" = Uuid, Path, description = "Quote id")),
+    request_body = QuoteInsert,
+    responses((status = 200, description = "The quote with its version bumped", body = Quote), (status = 404, description = "Quote not found", body = ProblemDetails, content_type = "application/problem+json")),
+)]
 pub async fn undo_quote_by_id(
     state: State<Arc<AppState>>,
     Path(id): Path<Uuid>,
@@ -91,6 +123,13 @@ pub async fn undo_quote_by_id(
     Ok((StatusCode::OK, Json(quote)))
 }
 
+#[utoipa::path(
+    post,
+    path = "/19/draft",
+    tag = "dec19",
+    request_body = QuoteInsert,
+    responses((status = 201, description = "The stored quote", body = Quote)),
+)]
 pub async fn draft_quote(
     state: State<Arc<AppState>>,
     Json(new_quote): Json<QuoteInsert>,
@@ -111,12 +150,14 @@ pub async fn draft_quote(
     Ok((StatusCode::CREATED, Json(quote)))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct PaginationParams {
+    /// `next_token` from the previous page
     token: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct PaginatedQuotes {
     quotes: Vec<Quote>,
     page: i32,
@@ -140,6 +181,16 @@ async fn retrieve_quotes_page(
     Ok(quote)
 }
 
+#[utoipa::path(
+    get,
+    path = "/19/list",
+    tag = "dec19",
+    params(PaginationParams),
+    responses(
+        (status = 200, description = "One page of quotes", body = PaginatedQuotes),
+        (status = 400, description = "Unknown pagination token", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+)]
 pub async fn paginated_quotes(
     state: State<Arc<AppState>>,
     Query(params): Query<PaginationParams>,
@@ -148,7 +199,7 @@ pub async fn paginated_quotes(
     let offset: i32;
 
     if let Some(token) = params.token {
-        let pagination_map = state.quote_pagination.try_read().unwrap();
+        let pagination_map = state.quote_pagination.read().await;
         match pagination_map.get(&token) {
             Some(offset_value) => offset = offset_value.clone() + PAGINATION_PAGE_SIZE,
             None => return Err(Day19AppError::BadToken),
@@ -158,8 +209,8 @@ pub async fn paginated_quotes(
     };
     state
         .quote_pagination
-        .try_write()
-        .unwrap()
+        .write()
+        .await
         .insert(new_token.clone(), offset);
 
     let quotes = retrieve_quotes_page(&offset, &state.pool).await?;
@@ -200,19 +251,34 @@ pub enum Day19AppError {
     BadToken,
 }
 
-impl IntoResponse for Day19AppError {
-    fn into_response(self) -> Response {
-        match self {
+impl From<Day19AppError> for AppError {
+    fn from(error: Day19AppError) -> Self {
+        match error {
             Day19AppError::SqlXError(rejection) => {
-                println!("{}", rejection);
+                tracing::warn!(error = %rejection, "quote query failed");
                 match rejection {
-                    sqlx::Error::RowNotFound => (StatusCode::NOT_FOUND, ""),
-                    _ => (StatusCode::BAD_REQUEST, ""),
+                    sqlx::Error::RowNotFound => {
+                        AppError::new(StatusCode::NOT_FOUND, "quote.not_found", "Quote not found")
+                    }
+                    _ => AppError::new(
+                        StatusCode::BAD_REQUEST,
+                        "quote.database_error",
+                        "Quote could not be stored or retrieved",
+                    ),
                 }
             }
-            Day19AppError::BadToken => (StatusCode::BAD_REQUEST, ""),
+            Day19AppError::BadToken => AppError::new(
+                StatusCode::BAD_REQUEST,
+                "quote.bad_token",
+                "Unknown pagination token",
+            ),
         }
-        .into_response()
+    }
+}
+
+impl IntoResponse for Day19AppError {
+    fn into_response(self) -> Response {
+        AppError::from(self).into_response()
     }
 }
 
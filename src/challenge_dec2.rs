@@ -1,91 +1,1094 @@
 use std::{
-    net::{Ipv4Addr, Ipv6Addr},
+    fmt::Display,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
     ops::BitXor,
+    str::FromStr,
+    sync::Arc,
 };
 
-use axum::{extract::Query, response::IntoResponse};
-use serde::Deserialize;
+use axum::{
+    body::{Body, Bytes},
+    extract::State,
+    http::{header::CONTENT_TYPE, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures_util::{stream, StreamExt};
+use hmac::{Hmac, Mac};
+use ipnet::Ipv4Net;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    error::{AppError, ProblemDetails},
+    extract::Query,
+    negotiation::accepts_json,
+    AppState,
+};
+
+/// Combines one octet of an address with the matching key octet. Every
+/// cipher comes with the inverse the `/key` routes rely on.
+pub trait Cipher {
+    fn encrypt(&self, from: u8, key: u8) -> u8;
+
+    /// A key octet for which `encrypt(from, key) == to`, if there is one.
+    fn derive_key(&self, from: u8, to: u8) -> Option<u8>;
+}
+
+/// Wrapping addition, the original IPv4 transform.
+pub struct AddCipher;
+
+impl Cipher for AddCipher {
+    fn encrypt(&self, from: u8, key: u8) -> u8 {
+        from.wrapping_add(key)
+    }
+
+    fn derive_key(&self, from: u8, to: u8) -> Option<u8> {
+        Some(to.wrapping_sub(from))
+    }
+}
+
+/// Exclusive or, the original IPv6 transform.
+pub struct XorCipher;
+
+impl Cipher for XorCipher {
+    fn encrypt(&self, from: u8, key: u8) -> u8 {
+        from.bitxor(key)
+    }
+
+    fn derive_key(&self, from: u8, to: u8) -> Option<u8> {
+        Some(from.bitxor(to))
+    }
+}
+
+/// Rotates the bits of each octet left by the key octet modulo 8. Only the
+/// rotations of `from` are reachable, so deriving a key can fail.
+pub struct RotateCipher;
+
+impl Cipher for RotateCipher {
+    fn encrypt(&self, from: u8, key: u8) -> u8 {
+        from.rotate_left(u32::from(key % 8))
+    }
+
+    fn derive_key(&self, from: u8, to: u8) -> Option<u8> {
+        (0..8).find(|&key| self.encrypt(from, key) == to)
+    }
+}
+
+/// XORs with the key, then pushes the octet through a fixed permutation of
+/// all 256 values.
+pub struct PermuteCipher;
+
+impl PermuteCipher {
+    const MULTIPLIER: u8 = 0x9d;
+    /// Inverse of `MULTIPLIER` modulo 256.
+    const MULTIPLIER_INVERSE: u8 = 0xb5;
+    const OFFSET: u8 = 0x3b;
+
+    fn permute(octet: u8) -> u8 {
+        octet
+            .wrapping_mul(Self::MULTIPLIER)
+            .wrapping_add(Self::OFFSET)
+            .rotate_left(3)
+    }
+
+    fn unpermute(octet: u8) -> u8 {
+        octet
+            .rotate_right(3)
+            .wrapping_sub(Self::OFFSET)
+            .wrapping_mul(Self::MULTIPLIER_INVERSE)
+    }
+}
+
+impl Cipher for PermuteCipher {
+    fn encrypt(&self, from: u8, key: u8) -> u8 {
+        Self::permute(from.bitxor(key))
+    }
 
-#[derive(Deserialize)]
+    fn derive_key(&self, from: u8, to: u8) -> Option<u8> {
+        Some(Self::unpermute(to).bitxor(from))
+    }
+}
+
+/// Cipher picked with `?cipher=`. IPv4 defaults to `add`, IPv6 and hardware
+/// addresses to `xor`.
+#[derive(Clone, Copy, Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CipherKind {
+    Add,
+    Xor,
+    Rotate,
+    Permute,
+}
+
+impl CipherKind {
+    fn cipher(self) -> &'static dyn Cipher {
+        match self {
+            CipherKind::Add => &AddCipher,
+            CipherKind::Xor => &XorCipher,
+            CipherKind::Rotate => &RotateCipher,
+            CipherKind::Permute => &PermuteCipher,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            CipherKind::Add => "add",
+            CipherKind::Xor => "xor",
+            CipherKind::Rotate => "rotate",
+            CipherKind::Permute => "permute",
+        }
+    }
+}
+
+impl Display for CipherKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct EgregiousEncryptionDestinationPacket<IPVersion> {
+    /// Source address
+    #[param(value_type = String)]
     from: IPVersion,
+    /// Key combined with every octet of `from`
+    #[param(value_type = String)]
     key: IPVersion,
+    /// Defaults to `add` for IPv4 and `xor` for everything else
+    cipher: Option<CipherKind>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct EgregiousEncryptionKeyPacket<IPVersion> {
+    /// Source address
+    #[param(value_type = String)]
     from: IPVersion,
+    /// Destination address
+    #[param(value_type = String)]
     to: IPVersion,
+    /// Defaults to `add` for IPv4 and `xor` for everything else
+    cipher: Option<CipherKind>,
+}
+
+fn encrypt_octets<const N: usize>(cipher: CipherKind, from: [u8; N], key: [u8; N]) -> [u8; N] {
+    let cipher = cipher.cipher();
+    std::array::from_fn(|i| cipher.encrypt(from[i], key[i]))
+}
+
+fn derive_key_octets<const N: usize>(
+    cipher: CipherKind,
+    from: [u8; N],
+    to: [u8; N],
+) -> Option<[u8; N]> {
+    let cipher = cipher.cipher();
+    let mut key = [0; N];
+    for (i, key_oct) in key.iter_mut().enumerate() {
+        *key_oct = cipher.derive_key(from[i], to[i])?;
+    }
+
+    Some(key)
+}
+
+fn encrypt_v4(cipher: CipherKind, from: Ipv4Addr, key: Ipv4Addr) -> Ipv4Addr {
+    Ipv4Addr::from(encrypt_octets(cipher, from.octets(), key.octets()))
+}
+
+/// The key that [`encrypt_v4`] needs to turn `from` into `to`.
+fn derive_key_v4(
+    cipher: CipherKind,
+    from: Ipv4Addr,
+    to: Ipv4Addr,
+) -> Result<Ipv4Addr, Day2AppError> {
+    derive_key_octets(cipher, from.octets(), to.octets())
+        .map(Ipv4Addr::from)
+        .ok_or_else(|| Day2AppError::NoKey(cipher, from.to_string(), to.to_string()))
+}
+
+fn encrypt_v6(cipher: CipherKind, from: Ipv6Addr, key: Ipv6Addr) -> Ipv6Addr {
+    Ipv6Addr::from(encrypt_octets(cipher, from.octets(), key.octets()))
+}
+
+/// The key that [`encrypt_v6`] needs to turn `from` into `to`.
+fn derive_key_v6(
+    cipher: CipherKind,
+    from: Ipv6Addr,
+    to: Ipv6Addr,
+) -> Result<Ipv6Addr, Day2AppError> {
+    derive_key_octets(cipher, from.octets(), to.octets())
+        .map(Ipv6Addr::from)
+        .ok_or_else(|| Day2AppError::NoKey(cipher, from.to_string(), to.to_string()))
+}
+
+/// A destination address in every form downstream tools asked for.
+#[derive(Serialize, ToSchema)]
+pub struct AddressRepresentation {
+    /// Dotted IPv4 or compressed IPv6 text
+    address: String,
+    /// Unsigned integer value, as a decimal string so IPv6 survives JSON
+    integer: String,
+    hex: String,
+    binary: String,
+    /// Reverse-DNS name under `in-addr.arpa` or `ip6.arpa`
+    ptr: String,
+    private: bool,
+    loopback: bool,
+    multicast: bool,
+    documentation: bool,
 }
 
+impl From<IpAddr> for AddressRepresentation {
+    fn from(address: IpAddr) -> Self {
+        let (bits, width): (u128, usize) = match address {
+            IpAddr::V4(v4) => (u128::from(v4.to_bits()), 32),
+            IpAddr::V6(v6) => (v6.to_bits(), 128),
+        };
+
+        let (ptr, private, documentation) = match address {
+            IpAddr::V4(v4) => (
+                v4.octets()
+                    .iter()
+                    .rev()
+                    .map(ToString::to_string)
+                    .chain(["in-addr.arpa".to_string()])
+                    .collect::<Vec<_>>()
+                    .join("."),
+                v4.is_private(),
+                v4.is_documentation(),
+            ),
+            IpAddr::V6(v6) => (
+                format!("{:032x}", v6.to_bits())
+                    .chars()
+                    .rev()
+                    .map(String::from)
+                    .chain(["ip6.arpa".to_string()])
+                    .collect::<Vec<_>>()
+                    .join("."),
+                // fc00::/7, unique local addresses
+                (v6.segments()[0] & 0xfe00) == 0xfc00,
+                // 2001:db8::/32 and 3fff::/20
+                matches!(v6.segments(), [0x2001, 0x0db8, ..])
+                    || matches!(v6.segments(), [0x3fff, second, ..] if second < 0x1000),
+            ),
+        };
+
+        AddressRepresentation {
+            address: address.to_string(),
+            integer: bits.to_string(),
+            hex: format!("0x{bits:0width$x}", width = width / 4),
+            binary: format!("{bits:0width$b}"),
+            ptr,
+            private,
+            loopback: address.is_loopback(),
+            multicast: address.is_multicast(),
+            documentation,
+        }
+    }
+}
+
+/// Plain text by default, every representation for `Accept: application/json`.
+fn address_response(headers: &HeaderMap, address: IpAddr) -> Response {
+    if accepts_json(headers) {
+        Json(AddressRepresentation::from(address)).into_response()
+    } else {
+        address.to_string().into_response()
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/2/dest",
+    tag = "dec2",
+    params(EgregiousEncryptionDestinationPacket<Ipv4Addr>),
+    responses((status = 200, description = "Destination address, as text or as JSON for `Accept: application/json`", content((String = "text/plain"), (AddressRepresentation = "application/json")))),
+)]
 pub async fn egregious_encryption_dest(
+    headers: HeaderMap,
     packet: Query<EgregiousEncryptionDestinationPacket<Ipv4Addr>>,
-) -> impl IntoResponse {
-    let calculated_octets: [u8; 4] = packet
-        .from
-        .octets()
-        .iter()
-        .zip(packet.key.octets())
-        .map(|(from_oct, key_oct)| from_oct.wrapping_add(key_oct))
-        .collect::<Vec<_>>()
-        .as_slice()
-        .try_into()
-        .unwrap();
-    let calculated_ip = Ipv4Addr::from(calculated_octets);
-
-    calculated_ip.to_string()
+) -> Response {
+    let cipher = packet.cipher.unwrap_or(CipherKind::Add);
+    address_response(&headers, encrypt_v4(cipher, packet.from, packet.key).into())
 }
 
+#[utoipa::path(
+    get,
+    path = "/2/key",
+    tag = "dec2",
+    params(EgregiousEncryptionKeyPacket<Ipv4Addr>),
+    responses((status = 200, description = "Key that maps `from` to `to`", body = String, content_type = "text/plain"), (status = 422, description = "The cipher cannot turn `from` into `to`", body = ProblemDetails, content_type = "application/problem+json")),
+)]
 pub async fn egregious_encryption_key(
     packet: Query<EgregiousEncryptionKeyPacket<Ipv4Addr>>,
-) -> impl IntoResponse {
-    let calculated_octets: [u8; 4] = packet
-        .to
-        .octets()
-        .iter()
-        .zip(packet.from.octets())
-        .map(|(to_oct, from_oct)| to_oct.wrapping_sub(from_oct))
-        .collect::<Vec<_>>()
-        .as_slice()
-        .try_into()
-        .unwrap();
-    let calculated_ip = Ipv4Addr::from(calculated_octets);
-
-    calculated_ip.to_string()
+) -> Result<impl IntoResponse, Day2AppError> {
+    let cipher = packet.cipher.unwrap_or(CipherKind::Add);
+    Ok(derive_key_v4(cipher, packet.from, packet.to)?.to_string())
 }
 
+#[utoipa::path(
+    get,
+    path = "/2/v6/dest",
+    tag = "dec2",
+    params(EgregiousEncryptionDestinationPacket<Ipv6Addr>),
+    responses((status = 200, description = "Destination address, as text or as JSON for `Accept: application/json`", content((String = "text/plain"), (AddressRepresentation = "application/json")))),
+)]
 pub async fn egregious_encryption_dest_v6(
+    headers: HeaderMap,
     packet: Query<EgregiousEncryptionDestinationPacket<Ipv6Addr>>,
-) -> impl IntoResponse {
-    let calculated_octets: [u8; 16] = packet
-        .from
-        .octets()
-        .iter()
-        .zip(packet.key.octets())
-        .map(|(from_oct, key_oct)| from_oct.bitxor(key_oct))
-        .collect::<Vec<_>>()
-        .as_slice()
-        .try_into()
-        .unwrap();
-    let calculated_ip = Ipv6Addr::from(calculated_octets);
-
-    calculated_ip.to_string()
+) -> Response {
+    let cipher = packet.cipher.unwrap_or(CipherKind::Xor);
+    address_response(&headers, encrypt_v6(cipher, packet.from, packet.key).into())
 }
 
+#[utoipa::path(
+    get,
+    path = "/2/v6/key",
+    tag = "dec2",
+    params(EgregiousEncryptionKeyPacket<Ipv6Addr>),
+    responses((status = 200, description = "Key that maps `from` to `to`", body = String, content_type = "text/plain"), (status = 422, description = "The cipher cannot turn `from` into `to`", body = ProblemDetails, content_type = "application/problem+json")),
+)]
 pub async fn egregious_encryption_key_v6(
     packet: Query<EgregiousEncryptionKeyPacket<Ipv6Addr>>,
+) -> Result<impl IntoResponse, Day2AppError> {
+    let cipher = packet.cipher.unwrap_or(CipherKind::Xor);
+    Ok(derive_key_v6(cipher, packet.from, packet.to)?.to_string())
+}
+
+/// Encrypts in the family both addresses share. IPv4-mapped IPv6 addresses
+/// (`::ffff:a.b.c.d`) count as IPv4, and the destination keeps the notation
+/// of `from`.
+fn encrypt_any(
+    cipher: Option<CipherKind>,
+    from: IpAddr,
+    key: IpAddr,
+) -> Result<IpAddr, Day2AppError> {
+    match (from.to_canonical(), key.to_canonical()) {
+        (IpAddr::V4(from_v4), IpAddr::V4(key)) => {
+            let dest = encrypt_v4(cipher.unwrap_or(CipherKind::Add), from_v4, key);
+            Ok(if from.is_ipv6() {
+                dest.to_ipv6_mapped().into()
+            } else {
+                dest.into()
+            })
+        }
+        (IpAddr::V6(from), IpAddr::V6(key)) => {
+            Ok(encrypt_v6(cipher.unwrap_or(CipherKind::Xor), from, key).into())
+        }
+        _ => Err(Day2AppError::FamilyMismatch(from, key)),
+    }
+}
+
+/// The key that [`encrypt_any`] needs to turn `from` into `to`, always in the
+/// family the addresses share.
+fn derive_key_any(
+    cipher: Option<CipherKind>,
+    from: IpAddr,
+    to: IpAddr,
+) -> Result<IpAddr, Day2AppError> {
+    match (from.to_canonical(), to.to_canonical()) {
+        (IpAddr::V4(from), IpAddr::V4(to)) => {
+            Ok(derive_key_v4(cipher.unwrap_or(CipherKind::Add), from, to)?.into())
+        }
+        (IpAddr::V6(from), IpAddr::V6(to)) => {
+            Ok(derive_key_v6(cipher.unwrap_or(CipherKind::Xor), from, to)?.into())
+        }
+        _ => Err(Day2AppError::FamilyMismatch(from, to)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/2/any/dest",
+    tag = "dec2",
+    params(EgregiousEncryptionDestinationPacket<IpAddr>),
+    responses((status = 200, description = "Destination address in the notation of `from`, as text or as JSON for `Accept: application/json`", content((String = "text/plain"), (AddressRepresentation = "application/json"))), (status = 400, description = "Addresses of different families", body = ProblemDetails, content_type = "application/problem+json")),
+)]
+pub async fn egregious_encryption_dest_any(
+    headers: HeaderMap,
+    packet: Query<EgregiousEncryptionDestinationPacket<IpAddr>>,
+) -> Result<Response, Day2AppError> {
+    let dest = encrypt_any(packet.cipher, packet.from, packet.key)?;
+    Ok(address_response(&headers, dest))
+}
+
+#[utoipa::path(
+    get,
+    path = "/2/any/key",
+    tag = "dec2",
+    params(EgregiousEncryptionKeyPacket<IpAddr>),
+    responses((status = 200, description = "Key that maps `from` to `to`", body = String, content_type = "text/plain"), (status = 400, description = "Addresses of different families", body = ProblemDetails, content_type = "application/problem+json"), (status = 422, description = "The cipher cannot turn `from` into `to`", body = ProblemDetails, content_type = "application/problem+json")),
+)]
+pub async fn egregious_encryption_key_any(
+    packet: Query<EgregiousEncryptionKeyPacket<IpAddr>>,
+) -> Result<impl IntoResponse, Day2AppError> {
+    Ok(derive_key_any(packet.cipher, packet.from, packet.to)?.to_string())
+}
+
+/// A hardware address of `N` octets written as hex pairs separated by `:`
+/// or `-`, e.g. `00:1a:2b:3c:4d:5e`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HardwareAddr<const N: usize>([u8; N]);
+
+/// 48-bit MAC address.
+pub type MacAddr = HardwareAddr<6>;
+
+/// EUI-64 interface identifier.
+pub type Eui64 = HardwareAddr<8>;
+
+impl<const N: usize> FromStr for HardwareAddr<N> {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s.split([':', '-']).collect::<Vec<_>>();
+        if parts.len() != N {
+            return Err(format!(
+                "expected {N} octets separated by ':' or '-', got {s:?}"
+            ));
+        }
+
+        let mut octets = [0; N];
+        for (octet, part) in octets.iter_mut().zip(parts) {
+            if part.len() != 2 {
+                return Err(format!("{part:?} is not a two-digit hex octet"));
+            }
+            *octet = u8::from_str_radix(part, 16)
+                .map_err(|_| format!("{part:?} is not a two-digit hex octet"))?;
+        }
+
+        Ok(HardwareAddr(octets))
+    }
+}
+
+impl<const N: usize> Display for HardwareAddr<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, octet) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ":")?;
+            }
+            write!(f, "{octet:02x}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'de, const N: usize> Deserialize<'de> for HardwareAddr<N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Modified EUI-64: `ff:fe` goes in the middle and the universal/local bit
+/// is flipped, as in SLAAC interface identifiers.
+impl From<MacAddr> for Eui64 {
+    fn from(mac: MacAddr) -> Self {
+        let [a, b, c, d, e, f] = mac.0;
+        HardwareAddr([a ^ 0x02, b, c, 0xff, 0xfe, d, e, f])
+    }
+}
+
+impl Eui64 {
+    /// The `fe80::/64` address with this interface identifier.
+    fn link_local(self) -> Ipv6Addr {
+        let mut octets = [0; 16];
+        octets[..2].copy_from_slice(&[0xfe, 0x80]);
+        octets[8..].copy_from_slice(&self.0);
+        Ipv6Addr::from(octets)
+    }
+}
+
+fn encrypt_hardware<const N: usize>(
+    cipher: Option<CipherKind>,
+    from: HardwareAddr<N>,
+    key: HardwareAddr<N>,
+) -> HardwareAddr<N> {
+    HardwareAddr(encrypt_octets(
+        cipher.unwrap_or(CipherKind::Xor),
+        from.0,
+        key.0,
+    ))
+}
+
+fn derive_key_hardware<const N: usize>(
+    cipher: Option<CipherKind>,
+    from: HardwareAddr<N>,
+    to: HardwareAddr<N>,
+) -> Result<HardwareAddr<N>, Day2AppError> {
+    let cipher = cipher.unwrap_or(CipherKind::Xor);
+    derive_key_octets(cipher, from.0, to.0)
+        .map(HardwareAddr)
+        .ok_or_else(|| Day2AppError::NoKey(cipher, from.to_string(), to.to_string()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/2/mac/dest",
+    tag = "dec2",
+    params(EgregiousEncryptionDestinationPacket<MacAddr>),
+    responses((status = 200, description = "Destination MAC address", body = String, content_type = "text/plain")),
+)]
+pub async fn egregious_encryption_dest_mac(
+    packet: Query<EgregiousEncryptionDestinationPacket<MacAddr>>,
+) -> impl IntoResponse {
+    encrypt_hardware(packet.cipher, packet.from, packet.key).to_string()
+}
+
+#[utoipa::path(
+    get,
+    path = "/2/mac/key",
+    tag = "dec2",
+    params(EgregiousEncryptionKeyPacket<MacAddr>),
+    responses((status = 200, description = "Key that maps `from` to `to`", body = String, content_type = "text/plain"), (status = 422, description = "The cipher cannot turn `from` into `to`", body = ProblemDetails, content_type = "application/problem+json")),
+)]
+pub async fn egregious_encryption_key_mac(
+    packet: Query<EgregiousEncryptionKeyPacket<MacAddr>>,
+) -> Result<impl IntoResponse, Day2AppError> {
+    Ok(derive_key_hardware(packet.cipher, packet.from, packet.to)?.to_string())
+}
+
+#[utoipa::path(
+    get,
+    path = "/2/mac/link-local",
+    tag = "dec2",
+    params(EgregiousEncryptionDestinationPacket<MacAddr>),
+    responses((status = 200, description = "IPv6 link-local address of the destination MAC address", body = String, content_type = "text/plain")),
+)]
+pub async fn egregious_encryption_link_local(
+    packet: Query<EgregiousEncryptionDestinationPacket<MacAddr>>,
 ) -> impl IntoResponse {
-    let calculated_octets: [u8; 16] = packet
-        .to
+    let mac = encrypt_hardware(packet.cipher, packet.from, packet.key);
+    Eui64::from(mac).link_local().to_string()
+}
+
+#[utoipa::path(
+    get,
+    path = "/2/eui64/dest",
+    tag = "dec2",
+    params(EgregiousEncryptionDestinationPacket<Eui64>),
+    responses((status = 200, description = "Destination EUI-64 identifier", body = String, content_type = "text/plain")),
+)]
+pub async fn egregious_encryption_dest_eui64(
+    packet: Query<EgregiousEncryptionDestinationPacket<Eui64>>,
+) -> impl IntoResponse {
+    encrypt_hardware(packet.cipher, packet.from, packet.key).to_string()
+}
+
+#[utoipa::path(
+    get,
+    path = "/2/eui64/key",
+    tag = "dec2",
+    params(EgregiousEncryptionKeyPacket<Eui64>),
+    responses((status = 200, description = "Key that maps `from` to `to`", body = String, content_type = "text/plain"), (status = 422, description = "The cipher cannot turn `from` into `to`", body = ProblemDetails, content_type = "application/problem+json")),
+)]
+pub async fn egregious_encryption_key_eui64(
+    packet: Query<EgregiousEncryptionKeyPacket<Eui64>>,
+) -> Result<impl IntoResponse, Day2AppError> {
+    Ok(derive_key_hardware(packet.cipher, packet.from, packet.to)?.to_string())
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CidrDestinationPacket {
+    /// Source network, e.g. `10.0.0.0/24`
+    #[param(value_type = String)]
+    from: Ipv4Net,
+    /// Key added to every octet of each address in `from`
+    #[param(value_type = String)]
+    key: Ipv4Addr,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CidrKeyPacket {
+    /// Source network
+    #[param(value_type = String)]
+    from: Ipv4Net,
+    /// Destination network, with the same prefix as `from`
+    #[param(value_type = String)]
+    to: Ipv4Net,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CidrDestination {
+    /// Block holding the destination of the source network address
+    #[schema(value_type = String, example = "11.2.3.0/24")]
+    network: Ipv4Net,
+    prefix: u8,
+    /// Whether every address of the source block lands inside `network`,
+    /// i.e. the destinations form a single CIDR block again
+    contiguous: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CidrKey {
+    #[schema(value_type = String, example = "1.2.3.0")]
+    key: Ipv4Addr,
+    prefix: u8,
+}
+
+/// Octets entirely inside the host part cover all 256 values before and after
+/// the wrapping add, so only an octet split by the prefix can break the block
+/// apart: that happens when the key has bits set below the prefix there.
+fn preserves_contiguity(network: Ipv4Net, key: Ipv4Addr) -> bool {
+    network
+        .hostmask()
         .octets()
         .iter()
-        .zip(packet.from.octets())
-        .map(|(to_oct, from_oct)| to_oct.bitxor(from_oct))
-        .collect::<Vec<_>>()
-        .as_slice()
-        .try_into()
-        .unwrap();
-    let calculated_ip = Ipv6Addr::from(calculated_octets);
-
-    calculated_ip.to_string()
+        .zip(key.octets())
+        .all(|(&host_bits, key_oct)| host_bits == u8::MAX || key_oct & host_bits == 0)
+}
+
+#[utoipa::path(
+    get,
+    path = "/2/cidr/dest",
+    tag = "dec2",
+    params(CidrDestinationPacket),
+    responses((status = 200, description = "Destination network", body = CidrDestination)),
+)]
+pub async fn egregious_encryption_cidr_dest(
+    packet: Query<CidrDestinationPacket>,
+) -> impl IntoResponse {
+    let from = packet.from.trunc();
+    let network = Ipv4Net::new(
+        encrypt_v4(CipherKind::Add, from.network(), packet.key),
+        from.prefix_len(),
+    )
+    .unwrap()
+    .trunc();
+
+    Json(CidrDestination {
+        network,
+        prefix: network.prefix_len(),
+        contiguous: preserves_contiguity(from, packet.key),
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/2/cidr/key",
+    tag = "dec2",
+    params(CidrKeyPacket),
+    responses((status = 200, description = "Key that maps the `from` block onto the `to` block", body = CidrKey), (status = 400, description = "Prefixes differ", body = ProblemDetails, content_type = "application/problem+json")),
+)]
+pub async fn egregious_encryption_cidr_key(
+    packet: Query<CidrKeyPacket>,
+) -> Result<impl IntoResponse, Day2AppError> {
+    if packet.from.prefix_len() != packet.to.prefix_len() {
+        return Err(Day2AppError::PrefixMismatch(packet.from, packet.to));
+    }
+
+    Ok(Json(CidrKey {
+        key: derive_key_v4(CipherKind::Add, packet.from.network(), packet.to.network())?,
+        prefix: packet.from.prefix_len(),
+    }))
+}
+
+/// One operation of a batch, handled like `/2/any/dest` and `/2/any/key`.
+#[derive(Deserialize, ToSchema)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum BatchOperation {
+    Dest {
+        #[schema(value_type = String)]
+        from: IpAddr,
+        #[schema(value_type = String)]
+        key: IpAddr,
+        cipher: Option<CipherKind>,
+    },
+    Key {
+        #[schema(value_type = String)]
+        from: IpAddr,
+        #[schema(value_type = String)]
+        to: IpAddr,
+        cipher: Option<CipherKind>,
+    },
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum BatchItemResult {
+    Result(String),
+    Error(BatchItemError),
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct BatchItemError {
+    code: &'static str,
+    detail: String,
+}
+
+impl From<AppError> for BatchItemError {
+    fn from(error: AppError) -> Self {
+        BatchItemError {
+            code: error.code(),
+            detail: error.detail().to_string(),
+        }
+    }
+}
+
+impl BatchOperation {
+    fn run(self) -> Result<IpAddr, Day2AppError> {
+        match self {
+            BatchOperation::Dest { from, key, cipher } => encrypt_any(cipher, from, key),
+            BatchOperation::Key { from, to, cipher } => derive_key_any(cipher, from, to),
+        }
+    }
+}
+
+fn run_batch_item(item: Value) -> BatchItemResult {
+    match serde_json::from_value::<BatchOperation>(item) {
+        Ok(operation) => match operation.run() {
+            Ok(address) => BatchItemResult::Result(address.to_string()),
+            Err(error) => BatchItemResult::Error(AppError::from(error).into()),
+        },
+        Err(error) => BatchItemResult::Error(BatchItemError {
+            code: "batch.invalid_item",
+            detail: error.to_string(),
+        }),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/2/batch",
+    tag = "dec2",
+    request_body(content = Vec<BatchOperation>, content_type = "application/json"),
+    responses((status = 200, description = "One result or error per operation, in order", body = Vec<BatchItemResult>), (status = 400, description = "Body is not a JSON array", body = ProblemDetails, content_type = "application/problem+json")),
+)]
+pub async fn egregious_encryption_batch(body: String) -> Result<impl IntoResponse, Day2AppError> {
+    let items = serde_json::from_str::<Vec<Value>>(&body)?;
+
+    Ok(Json(
+        items.into_iter().map(run_batch_item).collect::<Vec<_>>(),
+    ))
+}
+
+/// Longest `from,key` line the stream accepts. Anything longer is reported
+/// and skipped rather than buffered.
+const MAX_STREAM_LINE_LENGTH: usize = 128;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct StreamParams {
+    /// Defaults to `add` for IPv4 and `xor` for IPv6
+    cipher: Option<CipherKind>,
+}
+
+/// Splits the request body into lines as chunks arrive and turns each
+/// `from,key` line into one NDJSON result line.
+struct StreamTransformer {
+    cipher: Option<CipherKind>,
+    pending: Vec<u8>,
+    overlong: bool,
+}
+
+impl StreamTransformer {
+    fn new(cipher: Option<CipherKind>) -> Self {
+        StreamTransformer {
+            cipher,
+            pending: Vec::new(),
+            overlong: false,
+        }
+    }
+
+    fn feed(&mut self, chunk: &[u8]) -> Bytes {
+        let mut output = Vec::new();
+        let mut rest = chunk;
+        while let Some(newline) = rest.iter().position(|&byte| byte == b'\n') {
+            self.append(&rest[..newline], &mut output);
+            self.end_line(&mut output);
+            rest = &rest[newline + 1..];
+        }
+        self.append(rest, &mut output);
+
+        Bytes::from(output)
+    }
+
+    /// Flushes a last line that had no trailing newline.
+    fn finish(mut self) -> Bytes {
+        let mut output = Vec::new();
+        self.end_line(&mut output);
+
+        Bytes::from(output)
+    }
+
+    fn append(&mut self, bytes: &[u8], output: &mut Vec<u8>) {
+        if self.overlong {
+            return;
+        }
+
+        self.pending.extend_from_slice(bytes);
+        if self.pending.len() > MAX_STREAM_LINE_LENGTH {
+            self.pending.clear();
+            self.overlong = true;
+            write_stream_line(
+                output,
+                BatchItemResult::Error(BatchItemError {
+                    code: "stream.line_too_long",
+                    detail: format!("lines may be at most {MAX_STREAM_LINE_LENGTH} bytes"),
+                }),
+            );
+        }
+    }
+
+    fn end_line(&mut self, output: &mut Vec<u8>) {
+        let line = String::from_utf8_lossy(&self.pending);
+        let line = line.trim();
+        if !self.overlong && !line.is_empty() {
+            write_stream_line(output, transform_stream_line(self.cipher, line));
+        }
+
+        self.pending.clear();
+        self.overlong = false;
+    }
+}
+
+fn transform_stream_line(cipher: Option<CipherKind>, line: &str) -> BatchItemResult {
+    let Some((from, key)) = line.split_once(',') else {
+        return BatchItemResult::Error(BatchItemError {
+            code: "stream.invalid_line",
+            detail: format!("expected `from,key`, got {line:?}"),
+        });
+    };
+
+    let parsed = from
+        .trim()
+        .parse::<IpAddr>()
+        .and_then(|from| Ok((from, key.trim().parse::<IpAddr>()?)));
+    match parsed {
+        Ok((from, key)) => match encrypt_any(cipher, from, key) {
+            Ok(dest) => BatchItemResult::Result(dest.to_string()),
+            Err(error) => BatchItemResult::Error(AppError::from(error).into()),
+        },
+        Err(error) => BatchItemResult::Error(BatchItemError {
+            code: "stream.invalid_line",
+            detail: format!("{line:?}: {error}"),
+        }),
+    }
+}
+
+fn write_stream_line(output: &mut Vec<u8>, result: BatchItemResult) {
+    serde_json::to_writer(&mut *output, &result).unwrap();
+    output.push(b'\n');
+}
+
+#[utoipa::path(
+    post,
+    path = "/2/stream",
+    tag = "dec2",
+    params(StreamParams),
+    request_body(content = String, description = "One `from,key` pair per line", content_type = "text/plain"),
+    responses((status = 200, description = "One NDJSON `BatchItemResult` per non-empty input line, streamed in order", body = BatchItemResult, content_type = "application/x-ndjson")),
+)]
+pub async fn egregious_encryption_stream(
+    Query(params): Query<StreamParams>,
+    body: Body,
+) -> impl IntoResponse {
+    let chunks = body.into_data_stream();
+    let results = stream::unfold(
+        Some((chunks, StreamTransformer::new(params.cipher))),
+        |state| async move {
+            let (mut chunks, mut transformer) = state?;
+            match chunks.next().await {
+                Some(Ok(chunk)) => {
+                    let output = transformer.feed(&chunk);
+                    Some((Ok(output), Some((chunks, transformer))))
+                }
+                Some(Err(error)) => {
+                    tracing::warn!(%error, "address stream interrupted");
+                    Some((Err(error), None))
+                }
+                None => Some((Ok(transformer.finish()), None)),
+            }
+        },
+    );
+
+    (
+        [(CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(results),
+    )
+}
+
+/// Prefix-preserving anonymization in the style of Crypto-PAn: bit `i` of
+/// an address is flipped when an HMAC of the secret and the first `i` bits
+/// says so. Addresses sharing a prefix keep sharing it, and knowing the
+/// secret is enough to walk the bits back.
+pub struct Anonymizer {
+    secret: Vec<u8>,
+}
+
+impl Anonymizer {
+    pub fn new(secret: &str) -> Self {
+        Anonymizer {
+            secret: secret.as_bytes().to_vec(),
+        }
+    }
+
+    /// Whether bit `len` of an address whose first `len` bits are `prefix`
+    /// gets flipped. Addresses are left-aligned in a `u128`.
+    fn flips(&self, width: u32, prefix: u128, len: u32) -> bool {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.secret).expect("HMAC accepts keys of any length");
+        mac.update(&[width as u8, len as u8]);
+        mac.update(&prefix.to_be_bytes());
+        mac.finalize().into_bytes()[0] & 1 == 1
+    }
+
+    /// The key that [`encrypt_octets`] with the xor cipher uses to anonymize
+    /// `bits`.
+    fn derive_key(&self, width: u32, bits: u128) -> u128 {
+        (0..width)
+            .filter(|&len| self.flips(width, bits & prefix_mask(len), len))
+            .fold(0, |key, len| key | 1 << (127 - len))
+    }
+
+    fn deanonymize_bits(&self, width: u32, anonymized: u128) -> u128 {
+        (0..width).fold(0, |original, len| {
+            let bit = 1 << (127 - len);
+            if (anonymized & bit != 0) != self.flips(width, original, len) {
+                original | bit
+            } else {
+                original
+            }
+        })
+    }
+
+    /// IPv4-mapped addresses are anonymized as IPv4 and stay mapped.
+    pub fn anonymize(&self, address: IpAddr) -> IpAddr {
+        match address.to_canonical() {
+            IpAddr::V4(v4) => {
+                let key = self.derive_key(32, u128::from(v4.to_bits()) << 96);
+                let key = Ipv4Addr::from_bits((key >> 96) as u32);
+                keep_notation(address, encrypt_v4(CipherKind::Xor, v4, key).into())
+            }
+            IpAddr::V6(v6) => {
+                let key = Ipv6Addr::from_bits(self.derive_key(128, v6.to_bits()));
+                encrypt_v6(CipherKind::Xor, v6, key).into()
+            }
+        }
+    }
+
+    pub fn deanonymize(&self, address: IpAddr) -> IpAddr {
+        match address.to_canonical() {
+            IpAddr::V4(v4) => {
+                let bits = self.deanonymize_bits(32, u128::from(v4.to_bits()) << 96);
+                keep_notation(address, Ipv4Addr::from_bits((bits >> 96) as u32).into())
+            }
+            IpAddr::V6(v6) => Ipv6Addr::from_bits(self.deanonymize_bits(128, v6.to_bits())).into(),
+        }
+    }
+}
+
+/// The first `len` bits of a left-aligned address.
+fn prefix_mask(len: u32) -> u128 {
+    u128::MAX.checked_shl(128 - len).unwrap_or(0)
+}
+
+/// Maps `result` back to IPv6 when `original` was an IPv4-mapped address.
+fn keep_notation(original: IpAddr, result: IpAddr) -> IpAddr {
+    match (original, result) {
+        (IpAddr::V6(_), IpAddr::V4(v4)) => v4.to_ipv6_mapped().into(),
+        _ => result,
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AnonymizationParams {
+    /// IPv4 or IPv6 address
+    #[param(value_type = String)]
+    address: IpAddr,
+}
+
+fn anonymizer(state: &AppState) -> Result<&Anonymizer, Day2AppError> {
+    state.anonymizer.as_ref().ok_or(Day2AppError::NoSecret)
+}
+
+#[utoipa::path(
+    get,
+    path = "/2/anonymize",
+    tag = "dec2",
+    params(AnonymizationParams),
+    responses((status = 200, description = "Anonymized address, same family and notation", body = String, content_type = "text/plain"), (status = 503, description = "No anonymization secret configured", body = ProblemDetails, content_type = "application/problem+json")),
+)]
+pub async fn anonymize_address(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<AnonymizationParams>,
+) -> Result<impl IntoResponse, Day2AppError> {
+    Ok(anonymizer(&state)?.anonymize(params.address).to_string())
+}
+
+#[utoipa::path(
+    get,
+    path = "/2/deanonymize",
+    tag = "dec2",
+    params(AnonymizationParams),
+    responses((status = 200, description = "Original address", body = String, content_type = "text/plain"), (status = 503, description = "No anonymization secret configured", body = ProblemDetails, content_type = "application/problem+json")),
+)]
+pub async fn deanonymize_address(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<AnonymizationParams>,
+) -> Result<impl IntoResponse, Day2AppError> {
+    Ok(anonymizer(&state)?.deanonymize(params.address).to_string())
+}
+
+pub enum Day2AppError {
+    PrefixMismatch(Ipv4Net, Ipv4Net),
+    InvalidBatch(serde_json::Error),
+    FamilyMismatch(IpAddr, IpAddr),
+    NoKey(CipherKind, String, String),
+    NoSecret,
+}
+
+impl From<Day2AppError> for AppError {
+    fn from(error: Day2AppError) -> Self {
+        match error {
+            Day2AppError::PrefixMismatch(from, to) => AppError::new(
+                StatusCode::BAD_REQUEST,
+                "cidr.prefix_mismatch",
+                format!(
+                    "{from} and {to} have different prefixes, so no key maps one onto the other"
+                ),
+            ),
+            Day2AppError::InvalidBatch(rejection) => {
+                tracing::warn!(error = %rejection, "invalid batch body");
+                AppError::new(
+                    StatusCode::BAD_REQUEST,
+                    "batch.invalid",
+                    rejection.to_string(),
+                )
+            }
+            Day2AppError::FamilyMismatch(from, other) => AppError::new(
+                StatusCode::BAD_REQUEST,
+                "address.family_mismatch",
+                format!("{from} and {other} are not the same address family"),
+            ),
+            Day2AppError::NoKey(cipher, from, to) => AppError::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "cipher.no_key",
+                format!("no key makes the {cipher} cipher turn {from} into {to}"),
+            ),
+            Day2AppError::NoSecret => AppError::new(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "anonymize.not_configured",
+                "No anonymization_secret is configured",
+            ),
+        }
+    }
+}
+
+impl IntoResponse for Day2AppError {
+    fn into_response(self) -> Response {
+        AppError::from(self).into_response()
+    }
+}
+
+impl From<serde_json::Error> for Day2AppError {
+    fn from(rejection: serde_json::Error) -> Self {
+        Self::InvalidBatch(rejection)
+    }
 }
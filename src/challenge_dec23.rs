@@ -1,17 +1,37 @@
 use axum::{
     body::Bytes,
-    extract::{Multipart, Path},
     http::StatusCode,
     response::{IntoResponse, Response},
 };
 use serde::Deserialize;
 
+use crate::{
+    error::{AppError, ProblemDetails},
+    extract::{Multipart, Path},
+};
+
+#[utoipa::path(
+    get,
+    path = "/23/star",
+    tag = "dec23",
+    responses((status = 200, description = "Lit star", body = String, content_type = "text/html")),
+)]
 pub async fn htmx_star() -> impl IntoResponse {
     fhtml::format! {
         <div id="star" class="lit"></div>
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/23/present/{color}",
+    tag = "dec23",
+    params(("color" = String, Path, description = "`red`, `blue` or `purple`")),
+    responses(
+        (status = 200, description = "Present cycling to the next color", body = String, content_type = "text/html"),
+        (status = 418, description = "Unknown color", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+)]
 pub async fn htmx_present_color(
     Path(current_color): Path<String>,
 ) -> Result<impl IntoResponse, Day23AppError> {
@@ -32,6 +52,19 @@ pub async fn htmx_present_color(
     })
 }
 
+#[utoipa::path(
+    get,
+    path = "/23/ornament/{state}/{n}",
+    tag = "dec23",
+    params(
+        ("state" = String, Path, description = "`on` or `off`"),
+        ("n" = String, Path, description = "Ornament id"),
+    ),
+    responses(
+        (status = 200, description = "Ornament toggling after a delay", body = String, content_type = "text/html"),
+        (status = 418, description = "Unknown state", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+)]
 pub async fn htmx_css_animations(
     Path((animation_state, index)): Path<(String, String)>,
 ) -> Result<impl IntoResponse, Day23AppError> {
@@ -62,7 +95,24 @@ struct Package {
     checksum: Option<String>,
 }
 
-pub async fn htmx_form(mut multipart: Multipart) -> Result<impl IntoResponse, Day23AppError> {
+#[utoipa::path(
+    post,
+    path = "/23/lockfile",
+    tag = "dec23",
+    request_body(
+        content = String,
+        description = "Form with a `lockfile` field holding a Cargo.lock",
+        content_type = "multipart/form-data",
+    ),
+    responses(
+        (status = 200, description = "One colored div per checksummed package", body = String, content_type = "text/html"),
+        (status = 400, description = "Missing or unparsable lockfile", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 422, description = "Checksum is not valid hex", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+)]
+pub async fn htmx_form(
+    Multipart(mut multipart): Multipart,
+) -> Result<impl IntoResponse, Day23AppError> {
     let mut data = Bytes::new();
     while let Some(field) = multipart
         .next_field()
@@ -119,14 +169,35 @@ pub enum Day23AppError {
     BadChecksum,
 }
 
+impl From<Day23AppError> for AppError {
+    fn from(error: Day23AppError) -> Self {
+        match error {
+            Day23AppError::BadColor => AppError::new(
+                StatusCode::IM_A_TEAPOT,
+                "htmx.bad_color",
+                "Present color must be red, blue or purple",
+            ),
+            Day23AppError::BadState => AppError::new(
+                StatusCode::IM_A_TEAPOT,
+                "htmx.bad_state",
+                "Ornament state must be on or off",
+            ),
+            Day23AppError::BadFile => AppError::new(
+                StatusCode::BAD_REQUEST,
+                "htmx.bad_lockfile",
+                "Upload a valid Cargo.lock in the lockfile field",
+            ),
+            Day23AppError::BadChecksum => AppError::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "htmx.bad_checksum",
+                "Package checksum is not valid hex",
+            ),
+        }
+    }
+}
+
 impl IntoResponse for Day23AppError {
     fn into_response(self) -> Response {
-        match self {
-            Day23AppError::BadColor => (StatusCode::IM_A_TEAPOT, ""),
-            Day23AppError::BadState => (StatusCode::IM_A_TEAPOT, ""),
-            Day23AppError::BadFile => (StatusCode::BAD_REQUEST, ""),
-            Day23AppError::BadChecksum => (StatusCode::UNPROCESSABLE_ENTITY, ""),
-        }
-        .into_response()
+        AppError::from(self).into_response()
     }
 }
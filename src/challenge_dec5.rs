@@ -1,152 +1,1270 @@
-use std::str::FromStr;
+use std::{collections::BTreeMap, io, str::FromStr, sync::Arc};
 
 use axum::{
+    extract::State,
     http::{header::CONTENT_TYPE, HeaderMap, StatusCode},
     response::{IntoResponse, Response},
+    Json,
 };
-use cargo_manifest::Manifest;
+use cargo_manifest::{Dependency, Manifest};
 use serde::{Deserialize, Serialize};
 use toml::Table;
+use utoipa::ToSchema;
+
+use crate::{
+    config::{ManifestRules, RustVersion},
+    dependency_tree::{DependencyRequest, DependencyTree, Lockfile, Resolver},
+    error::{AppError, ProblemDetails},
+    extract::Multipart,
+    negotiation::{accepts_json, essence, preferred_media_type},
+    AppState,
+};
 
 #[derive(Debug, Deserialize, Serialize)]
 struct Config {
-    package: Package,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    package: Option<Package>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    workspace: Option<Workspace>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 struct Package {
     name: String,
-    metadata: Metadata,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<Metadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keywords: Option<Inheritable<Vec<String>>>,
+    #[serde(rename = "rust-version", skip_serializing_if = "Option::is_none")]
+    rust_version: Option<Inheritable<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    license: Option<Inheritable<String>>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Workspace {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    members: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    exclude: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    package: Option<WorkspacePackage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<Metadata>,
+}
+
+/// `[workspace.package]`, the values members inherit with `workspace = true`.
+#[derive(Debug, Deserialize, Serialize)]
+struct WorkspacePackage {
+    #[serde(skip_serializing_if = "Option::is_none")]
     keywords: Option<Vec<String>>,
-    #[serde(rename = "rust-version")]
+    #[serde(rename = "rust-version", skip_serializing_if = "Option::is_none")]
     rust_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    license: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+enum Inheritable<T> {
+    Local(T),
+    Workspace { workspace: bool },
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 struct Metadata {
-    orders: Vec<Table>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    orders: Option<Vec<Table>>,
+    #[serde(flatten)]
+    other: Table,
 }
 
-#[axum::debug_handler]
-pub async fn car_go_festivity(headers: HeaderMap, body: String) -> Result<String, Day5AppError> {
-    let content_header = headers.get(CONTENT_TYPE);
-    let content_type = content_header.unwrap().to_str().unwrap();
+impl Config {
+    fn workspace_package(&self) -> Option<&WorkspacePackage> {
+        self.workspace.as_ref()?.package.as_ref()
+    }
+
+    /// A package field, falling back to `[workspace.package]` when it is
+    /// inherited or when the manifest is a virtual workspace.
+    fn inherited<T>(
+        &self,
+        local: impl Fn(&Package) -> Option<&Inheritable<T>>,
+        workspace: impl Fn(&WorkspacePackage) -> Option<&T>,
+    ) -> Option<&T> {
+        match self.package.as_ref().and_then(local) {
+            Some(Inheritable::Local(value)) => Some(value),
+            Some(Inheritable::Workspace { .. }) => workspace(self.workspace_package()?),
+            None if self.package.is_none() => workspace(self.workspace_package()?),
+            None => None,
+        }
+    }
+
+    fn keywords(&self) -> Option<&Vec<String>> {
+        self.inherited(
+            |package| package.keywords.as_ref(),
+            |workspace| workspace.keywords.as_ref(),
+        )
+    }
+
+    fn rust_version(&self) -> Option<&String> {
+        self.inherited(
+            |package| package.rust_version.as_ref(),
+            |workspace| workspace.rust_version.as_ref(),
+        )
+    }
+
+    fn license(&self) -> Option<&String> {
+        self.inherited(
+            |package| package.license.as_ref(),
+            |workspace| workspace.license.as_ref(),
+        )
+    }
+
+    fn metadata(&self) -> impl Iterator<Item = &Metadata> {
+        let package = self
+            .package
+            .as_ref()
+            .and_then(|package| package.metadata.as_ref());
+        let workspace = self
+            .workspace
+            .as_ref()
+            .and_then(|workspace| workspace.metadata.as_ref());
+
+        package.into_iter().chain(workspace)
+    }
+
+    fn has_metadata_key(&self, key: &str) -> bool {
+        self.metadata().any(|metadata| match key {
+            "orders" => metadata.orders.is_some(),
+            _ => metadata.other.contains_key(key),
+        })
+    }
+
+    /// Orders of the package, then those in `[workspace.metadata]`, then
+    /// those of each workspace member.
+    fn into_orders(self) -> Vec<Table> {
+        let member_orders = self
+            .workspace
+            .as_ref()
+            .map(Workspace::member_orders)
+            .unwrap_or_default();
+        let package_orders = self.package.and_then(|package| package.metadata);
+        let workspace_orders = self.workspace.and_then(|workspace| workspace.metadata);
+
+        package_orders
+            .into_iter()
+            .chain(workspace_orders)
+            .flat_map(|metadata| metadata.orders.unwrap_or_default())
+            .chain(member_orders)
+            .collect()
+    }
+}
+
+impl Workspace {
+    /// Orders of the members, in `members` order. Only the root manifest is
+    /// uploaded, so a member's orders live in `[workspace.metadata."<path>"]`.
+    /// A glob like `crates/*` picks every matching table, `exclude` drops
+    /// paths and a path matched twice is read once.
+    fn member_orders(&self) -> Vec<Table> {
+        let Some(metadata) = &self.metadata else {
+            return Vec::new();
+        };
+
+        let mut paths: Vec<&String> = Vec::new();
+        for member in &self.members {
+            for path in metadata.other.keys() {
+                if member_matches(member, path)
+                    && !self
+                        .exclude
+                        .iter()
+                        .any(|excluded| member_matches(excluded, path))
+                    && !paths.contains(&path)
+                {
+                    paths.push(path);
+                }
+            }
+        }
+
+        paths
+            .into_iter()
+            .filter_map(|path| metadata.other[path].get("orders")?.as_array())
+            .flatten()
+            .filter_map(|order| order.as_table().cloned())
+            .collect()
+    }
+}
+
+/// Whether a `members` or `exclude` entry names `path`, with `*` matching
+/// within one path segment as cargo's globs do.
+fn member_matches(pattern: &str, path: &str) -> bool {
+    let pattern = pattern.trim_start_matches("./").trim_end_matches('/');
+    let path = path.trim_start_matches("./").trim_end_matches('/');
+    if pattern.split('/').count() != path.split('/').count() {
+        return false;
+    }
+
+    pattern
+        .split('/')
+        .zip(path.split('/'))
+        .all(|(pattern, segment)| match pattern.split_once('*') {
+            Some((prefix, suffix)) => {
+                segment.len() >= prefix.len() + suffix.len()
+                    && segment.starts_with(prefix)
+                    && segment.ends_with(suffix)
+            }
+            None => pattern == segment,
+        })
+}
+
+/// An entry of `metadata.orders` that passed validation.
+#[derive(Serialize, ToSchema)]
+pub struct Order {
+    item: String,
+    quantity: i64,
+    /// What the quantity counts, e.g. `box`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unit: Option<String>,
+    /// Price of one unit, in cents.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    price_cents: Option<i64>,
+    /// `quantity * price_cents`, for orders with a price.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    total_cents: Option<i64>,
+}
+
+/// Prices are kept in whole cents, so they may have at most two decimals.
+const CENTS_PER_UNIT: i64 = 100;
+
+#[derive(Clone, Copy, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderRejection {
+    MissingItem,
+    NonIntegerQuantity,
+    NegativeQuantity,
+    InvalidUnit,
+    InvalidPrice,
+    /// The price has more than two decimals.
+    TooPrecisePrice,
+    /// The order's own total, the item's summed quantity or the invoice
+    /// total would not fit in an `i64`.
+    TotalOverflow,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SkippedOrder {
+    /// Position of the order in the manifest, counting from 0.
+    index: usize,
+    #[schema(value_type = Object)]
+    order: Table,
+    /// Every problem found with the order.
+    reasons: Vec<OrderRejection>,
+}
+
+/// The JSON answer of `/5/manifest`.
+#[derive(Default, Serialize, ToSchema)]
+pub struct OrderSummary {
+    orders: Vec<Order>,
+    skipped: Vec<SkippedOrder>,
+    /// Summed quantity per item.
+    totals: BTreeMap<String, i64>,
+    /// Sum of the order totals, in cents. Orders without a price add nothing.
+    invoice_total_cents: i64,
+}
+
+impl OrderSummary {
+    /// Every overflow skips the order that caused it, whether in its own
+    /// total or in one of the sums it would add to.
+    fn new(orders: Vec<Table>) -> Self {
+        let mut summary = OrderSummary::default();
+
+        for (index, table) in orders.into_iter().enumerate() {
+            let order = Order::try_from(&table).and_then(|order| {
+                let item_total = summary
+                    .totals
+                    .get(&order.item)
+                    .copied()
+                    .unwrap_or_default()
+                    .checked_add(order.quantity);
+                let invoice_total = summary
+                    .invoice_total_cents
+                    .checked_add(order.total_cents.unwrap_or_default());
+                match (item_total, invoice_total) {
+                    (Some(item_total), Some(invoice_total)) => {
+                        Ok((order, item_total, invoice_total))
+                    }
+                    _ => Err(vec![OrderRejection::TotalOverflow]),
+                }
+            });
+
+            match order {
+                Ok((order, item_total, invoice_total)) => {
+                    summary.totals.insert(order.item.clone(), item_total);
+                    summary.invoice_total_cents = invoice_total;
+                    summary.orders.push(order);
+                }
+                Err(reasons) => summary.skipped.push(SkippedOrder {
+                    index,
+                    order: table,
+                    reasons,
+                }),
+            }
+        }
+
+        summary
+    }
+}
+
+/// The plain-text answer, one `item: quantity` line per order with an item
+/// and an integer quantity. It keeps the original leniency: units, prices and
+/// negative quantities are only validated for the JSON summary.
+fn order_lines(orders: &[Table]) -> Vec<String> {
+    orders
+        .iter()
+        .filter_map(|order| {
+            let item = order.get("item")?.as_str()?;
+            let quantity = order.get("quantity")?.as_integer()?;
+            Some(format!("{item}: {quantity}"))
+        })
+        .collect()
+}
+
+impl TryFrom<&Table> for Order {
+    type Error = Vec<OrderRejection>;
+
+    /// Checks every field, so a rejection lists all of the order's problems.
+    fn try_from(order: &Table) -> Result<Self, Self::Error> {
+        let mut rejections = Vec::new();
+
+        let item = order.get("item").and_then(|item| item.as_str());
+        if item.is_none() {
+            rejections.push(OrderRejection::MissingItem);
+        }
+
+        let quantity = order
+            .get("quantity")
+            .and_then(|quantity| quantity.as_integer());
+        match quantity {
+            None => rejections.push(OrderRejection::NonIntegerQuantity),
+            Some(quantity) if quantity < 0 => rejections.push(OrderRejection::NegativeQuantity),
+            Some(_) => {}
+        }
+
+        let unit = match order.get("unit") {
+            None => None,
+            Some(toml::Value::String(unit)) => Some(unit.clone()),
+            Some(_) => {
+                rejections.push(OrderRejection::InvalidUnit);
+                None
+            }
+        };
+
+        let price_cents = match order.get("price").map(price_in_cents) {
+            None => None,
+            Some(Ok(price_cents)) => Some(price_cents),
+            Some(Err(rejection)) => {
+                rejections.push(rejection);
+                None
+            }
+        };
+
+        match (item, quantity) {
+            (Some(item), Some(quantity)) if rejections.is_empty() => {
+                let total_cents = match price_cents.map(|price| price.checked_mul(quantity)) {
+                    Some(None) => return Err(vec![OrderRejection::TotalOverflow]),
+                    total_cents => total_cents.flatten(),
+                };
+
+                Ok(Order {
+                    item: item.to_string(),
+                    quantity,
+                    unit,
+                    price_cents,
+                    total_cents,
+                })
+            }
+            _ => Err(rejections),
+        }
+    }
+}
+
+/// A non-negative price in cents. A float price must name a whole number of
+/// cents and be small enough for `f64` to hold every cent up to it exactly.
+fn price_in_cents(price: &toml::Value) -> Result<i64, OrderRejection> {
+    const MAX_EXACT_CENTS: f64 = (1u64 << f64::MANTISSA_DIGITS) as f64;
+
+    match *price {
+        toml::Value::Integer(price) if price >= 0 => price
+            .checked_mul(CENTS_PER_UNIT)
+            .ok_or(OrderRejection::InvalidPrice),
+        toml::Value::Float(price) if price.is_finite() && price >= 0.0 => {
+            let cents = (price * CENTS_PER_UNIT as f64).round();
+            if cents > MAX_EXACT_CENTS {
+                return Err(OrderRejection::InvalidPrice);
+            }
+            if cents / CENTS_PER_UNIT as f64 != price {
+                return Err(OrderRejection::TooPrecisePrice);
+            }
+            Ok(cents as i64)
+        }
+        _ => Err(OrderRejection::InvalidPrice),
+    }
+}
+
+/// The configured check a manifest failed, named in the rejection.
+#[derive(Clone, Copy, Debug)]
+pub enum ManifestRule {
+    RustVersionFormat,
+    MinRustVersion,
+    MaxRustVersion,
+    RequiredKeywords,
+    AllowedLicenses,
+    RequiredMetadataKeys,
+}
 
-    let content: String;
-    let config: Config;
-    let manifest: Manifest;
+impl ManifestRule {
+    fn name(self) -> &'static str {
+        match self {
+            ManifestRule::RustVersionFormat => "rust_version_format",
+            ManifestRule::MinRustVersion => "min_rust_version",
+            ManifestRule::MaxRustVersion => "max_rust_version",
+            ManifestRule::RequiredKeywords => "required_keywords",
+            ManifestRule::AllowedLicenses => "allowed_licenses",
+            ManifestRule::RequiredMetadataKeys => "required_metadata_keys",
+        }
+    }
+
+    fn code(self) -> &'static str {
+        match self {
+            ManifestRule::RustVersionFormat => "manifest.rule.rust_version_format",
+            ManifestRule::MinRustVersion => "manifest.rule.min_rust_version",
+            ManifestRule::MaxRustVersion => "manifest.rule.max_rust_version",
+            ManifestRule::RequiredKeywords => "manifest.rule.required_keywords",
+            ManifestRule::AllowedLicenses => "manifest.rule.allowed_licenses",
+            ManifestRule::RequiredMetadataKeys => "manifest.rule.required_metadata_keys",
+        }
+    }
+}
 
-    match content_type {
-        "application/toml" => {
-            manifest = Manifest::from_slice(&body.as_bytes())?;
-            content = String::from(body.as_str());
+fn check_rules(config: &Config, rules: &ManifestRules) -> Result<(), Day5AppError> {
+    let failed = |rule, detail: String| Err(Day5AppError::RuleFailed(rule, detail));
+
+    if let Some(version) = config.rust_version() {
+        let Ok(parsed) = version.parse::<RustVersion>() else {
+            return failed(
+                ManifestRule::RustVersionFormat,
+                format!("{version:?} is not a version like 1.70 or 1.70.0"),
+            );
+        };
+        if let Some(min) = rules.min_rust_version.filter(|min| parsed < *min) {
+            return failed(
+                ManifestRule::MinRustVersion,
+                format!("rust-version {version} is below {min}"),
+            );
         }
-        "application/yaml" => {
-            config = serde_yaml::from_slice(&body.as_bytes())?;
-            let re_toml = toml::to_string(&config).unwrap();
-            content = re_toml.clone();
-            manifest = Manifest::from_str(&re_toml)?;
+        if let Some(max) = rules.max_rust_version.filter(|max| parsed > *max) {
+            return failed(
+                ManifestRule::MaxRustVersion,
+                format!("rust-version {version} is above {max}"),
+            );
         }
-        "application/json" => {
-            config = serde_json::from_slice(&body.as_bytes())?;
-            let re_toml = toml::to_string(&config).unwrap();
-            content = re_toml.clone();
-            manifest = Manifest::from_str(&re_toml)?;
+    }
+
+    let keywords = config.keywords();
+    if let Some(missing) = rules
+        .required_keywords
+        .iter()
+        .find(|required| !keywords.is_some_and(|keywords| keywords.contains(*required)))
+    {
+        return failed(
+            ManifestRule::RequiredKeywords,
+            format!("keyword {missing:?} is missing"),
+        );
+    }
+
+    if !rules.allowed_licenses.is_empty() {
+        match config.license() {
+            None => {
+                return failed(
+                    ManifestRule::AllowedLicenses,
+                    String::from("no license is set"),
+                )
+            }
+            Some(license) => {
+                if let Some(id) = license_ids(license)
+                    .find(|id| !rules.allowed_licenses.iter().any(|allowed| allowed == id))
+                {
+                    return failed(
+                        ManifestRule::AllowedLicenses,
+                        format!("license {id:?} is not allowed"),
+                    );
+                }
+            }
         }
-        _ => return Err(Day5AppError::UnsupportedMediaType),
     }
 
-    let package = manifest.package.unwrap();
+    if let Some(missing) = rules
+        .required_metadata_keys
+        .iter()
+        .find(|key| !config.has_metadata_key(key))
+    {
+        return failed(
+            ManifestRule::RequiredMetadataKeys,
+            format!("metadata key {missing:?} is missing"),
+        );
+    }
 
-    if let Some(version) = package.rust_version {
-        if version
-            .as_local()
-            .unwrap()
-            .chars()
+    Ok(())
+}
+
+/// The license identifiers of an SPDX expression like `MIT OR Apache-2.0`,
+/// also splitting the legacy `MIT/Apache-2.0` form. The exception named after
+/// `WITH` is not a license and is skipped.
+fn license_ids(license: &str) -> impl Iterator<Item = &str> {
+    let mut tokens = license
+        .split(|char: char| char.is_whitespace() || matches!(char, '/' | '(' | ')'))
+        .filter(|token| !token.is_empty());
+
+    std::iter::from_fn(move || loop {
+        match tokens.next()? {
+            "WITH" => {
+                tokens.next();
+            }
+            "OR" | "AND" => {}
+            id => return Some(id),
+        }
+    })
+}
+
+/// A `Cargo.lock` has a top-level `[[package]]` array where a manifest has a
+/// `[package]` table.
+fn is_lockfile_table(table: &Table) -> bool {
+    table
+        .get("package")
+        .is_some_and(|package| package.is_array())
+}
+
+/// Formats a manifest can be uploaded in and converted to.
+#[derive(Clone, Copy)]
+enum ManifestFormat {
+    Toml,
+    Yaml,
+    Json,
+}
+
+impl ManifestFormat {
+    const ALL: [ManifestFormat; 3] = [
+        ManifestFormat::Toml,
+        ManifestFormat::Yaml,
+        ManifestFormat::Json,
+    ];
+
+    fn media_type(self) -> &'static str {
+        match self {
+            ManifestFormat::Toml => "application/toml",
+            ManifestFormat::Yaml => "application/yaml",
+            ManifestFormat::Json => "application/json",
+        }
+    }
+
+    /// Ignores parameters, so `application/json; charset=utf-8` is JSON.
+    fn from_media_type(media_type: &str) -> Option<Self> {
+        let media_type = essence(media_type);
+        ManifestFormat::ALL
             .into_iter()
-            .any(|char| char.is_alphabetic())
-        {
-            return Err(Day5AppError::ManifestError);
+            .find(|format| format.media_type().eq_ignore_ascii_case(media_type))
+    }
+
+    /// The format of the upload, from its `Content-Type`.
+    fn of_request(headers: &HeaderMap) -> Result<Self, Day5AppError> {
+        headers
+            .get(CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .and_then(ManifestFormat::from_media_type)
+            .ok_or(Day5AppError::UnsupportedMediaType)
+    }
+
+    /// The format to answer in, from the `Accept` header, TOML by default.
+    fn accepted(headers: &HeaderMap) -> Result<Self, Day5AppError> {
+        preferred_media_type(
+            headers,
+            &ManifestFormat::ALL.map(ManifestFormat::media_type),
+        )
+        .and_then(ManifestFormat::from_media_type)
+        .ok_or(Day5AppError::NotAcceptable)
+    }
+
+    fn read(self, manifest: &str) -> Result<Table, Day5AppError> {
+        match self {
+            ManifestFormat::Toml => toml::from_str(manifest).map_err(Day5AppError::InvalidToml),
+            ManifestFormat::Yaml => Ok(serde_yaml::from_str(manifest)?),
+            ManifestFormat::Json => Ok(serde_json::from_str(manifest)?),
         }
     }
 
-    if let Some(keywords) = package.keywords {
-        let keywords = keywords.as_local().unwrap();
-        if !keywords.contains(&String::from("Christmas 2024")) {
-            return Err(Day5AppError::NoMagicKeyword);
+    fn write(self, manifest: &Table) -> Result<String, Day5AppError> {
+        match self {
+            ManifestFormat::Toml => toml::to_string(manifest).map_err(|e| e.to_string()),
+            ManifestFormat::Yaml => serde_yaml::to_string(manifest).map_err(|e| e.to_string()),
+            ManifestFormat::Json => {
+                serde_json::to_string_pretty(manifest).map_err(|e| e.to_string())
+            }
         }
-    } else {
-        return Err(Day5AppError::NoMagicKeyword);
+        .map_err(Day5AppError::ConversionError)
+    }
+}
+
+/// The uploaded manifest as TOML, converting YAML and JSON uploads. A
+/// lockfile is rejected the same way whatever its format, before the
+/// conversion could fail on it.
+fn manifest_content(headers: &HeaderMap, body: String) -> Result<String, Day5AppError> {
+    let format = ManifestFormat::of_request(headers)?;
+    if format
+        .read(&body)
+        .is_ok_and(|manifest| is_lockfile_table(&manifest))
+    {
+        return Err(Day5AppError::Lockfile);
     }
 
+    match format {
+        ManifestFormat::Toml => Ok(body),
+        ManifestFormat::Yaml => {
+            let config: Config = serde_yaml::from_slice(body.as_bytes())?;
+            Ok(toml::to_string(&config).unwrap())
+        }
+        ManifestFormat::Json => {
+            let config: Config = serde_json::from_slice(body.as_bytes())?;
+            Ok(toml::to_string(&config).unwrap())
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/5/manifest",
+    tag = "dec5",
+    request_body(
+        description = "Cargo manifest with `package.metadata.orders`, or a workspace root with `workspace.metadata.orders` and per-member `workspace.metadata.\"<member path>\".orders`",
+        content(
+            (String = "application/toml"),
+            (String = "application/yaml"),
+            (String = "application/json"),
+        ),
+    ),
+    responses(
+        (status = 200, description = "One `item: quantity` line per order with an item and an integer quantity, or the validated summary when `application/json` is preferred", content(
+            (String = "text/plain"),
+            (OrderSummary = "application/json"),
+        )),
+        (status = 204, description = "No valid orders, for plain text answers"),
+        (status = 400, description = "Invalid manifest, a Cargo.lock in any format, neither package nor workspace, or a failed `manifest_rules` check", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 415, description = "Unsupported content type", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+)]
+#[axum::debug_handler]
+pub async fn car_go_festivity(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<Response, Day5AppError> {
+    let content = manifest_content(&headers, body)?;
+
+    Manifest::from_str(&content)?;
     let config = toml::from_str::<Config>(&content)?;
 
-    let order_items = config
-        .package
-        .metadata
-        .orders
-        .into_iter()
-        .filter_map(|order| {
-            let item = order.get("item").unwrap();
-            let quantity = order.get("quantity");
+    if config.package.is_none() && config.workspace.is_none() {
+        return Err(Day5AppError::NotAPackage);
+    }
 
-            if quantity.is_some_and(|q| q.is_integer()) {
-                return Some(format!(
-                    "{}: {}",
-                    item.as_str().unwrap(),
-                    quantity.unwrap().as_integer().unwrap()
-                ));
-            }
+    check_rules(&config, &state.manifest_rules)?;
+
+    let orders = config.into_orders();
+
+    if accepts_json(&headers) {
+        return Ok(Json(OrderSummary::new(orders)).into_response());
+    }
+
+    let lines = order_lines(&orders);
+    if lines.is_empty() {
+        return Err(Day5AppError::NoContent);
+    }
+
+    Ok(lines.join("\n").into_response())
+}
+
+const KNOWN_EDITIONS: [&str; 4] = ["2015", "2018", "2021", "2024"];
+
+const DEPENDENCY_TABLES: [&str; 5] = [
+    "dependencies",
+    "dev-dependencies",
+    "dev_dependencies",
+    "build-dependencies",
+    "build_dependencies",
+];
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LintSeverity {
+    Error,
+    Warning,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct LintFinding {
+    /// Stable identifier of the check, e.g. `dependency.wildcard_version`.
+    rule: &'static str,
+    severity: LintSeverity,
+    /// Dotted path of the offending key, e.g. `dependencies.serde`.
+    path: String,
+    message: String,
+}
+
+impl LintFinding {
+    fn error(rule: &'static str, path: impl Into<String>, message: impl Into<String>) -> Self {
+        LintFinding {
+            rule,
+            severity: LintSeverity::Error,
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+
+    fn warning(rule: &'static str, path: impl Into<String>, message: impl Into<String>) -> Self {
+        LintFinding {
+            rule,
+            severity: LintSeverity::Warning,
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+}
 
-            None
+/// Lints the raw TOML rather than a `cargo_manifest::Manifest`, so values the
+/// latter refuses to parse, like an unknown edition, still become findings.
+/// `dependencies`, `dev-dependencies` or `build-dependencies`, folding the
+/// underscore spellings cargo also accepts.
+fn dependency_kind(table: &str) -> &str {
+    match table {
+        "dev_dependencies" => "dev-dependencies",
+        "build_dependencies" => "build-dependencies",
+        table => table,
+    }
+}
+
+fn lint_manifest(manifest: &Table) -> Vec<LintFinding> {
+    let mut findings = Vec::new();
+
+    for (section, table) in [
+        ("package", manifest.get("package")),
+        (
+            "workspace.package",
+            manifest
+                .get("workspace")
+                .and_then(|workspace| workspace.get("package")),
+        ),
+    ] {
+        if let Some(table) = table.and_then(|table| table.as_table()) {
+            lint_package_fields(section, table, &mut findings);
+        }
+    }
+
+    if let Some(package) = manifest
+        .get("package")
+        .and_then(|package| package.as_table())
+    {
+        if !package.contains_key("license") && !package.contains_key("license-file") {
+            findings.push(LintFinding::warning(
+                "package.missing_license",
+                "package.license",
+                "Neither license nor license-file is set",
+            ));
+        }
+        if !package.contains_key("description") {
+            findings.push(LintFinding::warning(
+                "package.missing_description",
+                "package.description",
+                "No description is set",
+            ));
+        }
+    }
+
+    // Each table with the scope it shares with its aliases: `dev-dependencies`
+    // and `dev_dependencies` of the same target are one scope.
+    let mut dependency_tables = DEPENDENCY_TABLES
+        .iter()
+        .filter_map(|name| {
+            let dependencies = manifest.get(*name)?.as_table()?;
+            Some((
+                name.to_string(),
+                dependency_kind(name).to_string(),
+                dependencies,
+            ))
         })
         .collect::<Vec<_>>();
+    if let Some(targets) = manifest.get("target").and_then(|target| target.as_table()) {
+        for (target, table) in targets {
+            for name in DEPENDENCY_TABLES {
+                if let Some(dependencies) = table.get(name).and_then(|table| table.as_table()) {
+                    dependency_tables.push((
+                        format!("target.{target}.{name}"),
+                        format!("target.{target}.{}", dependency_kind(name)),
+                        dependencies,
+                    ));
+                }
+            }
+        }
+    }
 
-    if order_items.len() == 0 {
-        return Err(Day5AppError::NoContent);
+    // A crate in both `[dependencies]` and `[dev-dependencies]` is fine, e.g.
+    // to enable extra features in tests; only a repeat within a scope is not.
+    let mut seen_in = BTreeMap::<(&str, &str), &str>::new();
+    for (table_path, scope, dependencies) in &dependency_tables {
+        for (name, dependency) in dependencies.iter() {
+            let path = format!("{table_path}.{name}");
+            lint_dependency(&path, dependency, &mut findings);
+
+            let package = dependency
+                .get("package")
+                .and_then(|package| package.as_str())
+                .unwrap_or(name);
+            match seen_in.get(&(scope.as_str(), package)) {
+                Some(first) => findings.push(LintFinding::warning(
+                    "dependency.duplicate",
+                    path,
+                    format!("{package} is also declared in {first}"),
+                )),
+                None => {
+                    seen_in.insert((scope.as_str(), package), table_path.as_str());
+                }
+            }
+        }
+    }
+
+    if let Some(dependencies) = manifest
+        .get("workspace")
+        .and_then(|workspace| workspace.get("dependencies"))
+        .and_then(|dependencies| dependencies.as_table())
+    {
+        for (name, dependency) in dependencies {
+            lint_dependency(
+                &format!("workspace.dependencies.{name}"),
+                dependency,
+                &mut findings,
+            );
+        }
+    }
+
+    findings
+}
+
+fn lint_package_fields(section: &str, package: &Table, findings: &mut Vec<LintFinding>) {
+    if let Some(version) = package.get("version").and_then(|version| version.as_str()) {
+        if semver::Version::parse(version).is_err() {
+            findings.push(LintFinding::error(
+                "package.invalid_version",
+                format!("{section}.version"),
+                format!("{version:?} is not a semver version like 1.2.3"),
+            ));
+        }
+    }
+
+    if let Some(version) = package
+        .get("rust-version")
+        .and_then(|version| version.as_str())
+    {
+        if version.parse::<RustVersion>().is_err() {
+            findings.push(LintFinding::error(
+                "package.invalid_rust_version",
+                format!("{section}.rust-version"),
+                format!("{version:?} is not a version like 1.70 or 1.70.0"),
+            ));
+        }
     }
 
-    Ok(order_items.join("\n"))
+    if let Some(edition) = package.get("edition").and_then(|edition| edition.as_str()) {
+        if !KNOWN_EDITIONS.contains(&edition) {
+            findings.push(LintFinding::error(
+                "package.unknown_edition",
+                format!("{section}.edition"),
+                format!("{edition:?} is not one of {}", KNOWN_EDITIONS.join(", ")),
+            ));
+        }
+    }
+}
+
+fn lint_dependency(path: &str, dependency: &toml::Value, findings: &mut Vec<LintFinding>) {
+    let version = match dependency {
+        toml::Value::String(version) => Some(version.as_str()),
+        _ => dependency
+            .get("version")
+            .and_then(|version| version.as_str()),
+    };
+    if version.is_some_and(|version| version.trim() == "*") {
+        findings.push(LintFinding::warning(
+            "dependency.wildcard_version",
+            path,
+            "Wildcard version requirement accepts any release",
+        ));
+    }
+
+    if dependency.get("git").is_some() && dependency.get("rev").is_none() {
+        findings.push(LintFinding::warning(
+            "dependency.git_without_rev",
+            path,
+            "Git dependency is not pinned to a rev",
+        ));
+    }
+}
+
+/// The uploaded manifest as a TOML table, keeping every field of YAML and
+/// JSON uploads.
+fn manifest_table(headers: &HeaderMap, body: &str) -> Result<Table, Day5AppError> {
+    ManifestFormat::of_request(headers)?.read(body)
+}
+
+#[utoipa::path(
+    post,
+    path = "/5/lint",
+    tag = "dec5",
+    request_body(
+        description = "Cargo manifest or workspace root",
+        content(
+            (String = "application/toml"),
+            (String = "application/yaml"),
+            (String = "application/json"),
+        ),
+    ),
+    responses(
+        (status = 200, description = "Every finding, empty for a clean manifest", body = Vec<LintFinding>),
+        (status = 400, description = "Unparseable manifest or lockfile", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 415, description = "Unsupported content type", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+)]
+pub async fn lint_cargo_manifest(
+    headers: HeaderMap,
+    body: String,
+) -> Result<Json<Vec<LintFinding>>, Day5AppError> {
+    let manifest = manifest_table(&headers, &body)?;
+
+    if is_lockfile_table(&manifest) {
+        return Err(Day5AppError::Lockfile);
+    }
+
+    Ok(Json(lint_manifest(&manifest)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/5/convert",
+    tag = "dec5",
+    request_body(
+        description = "Any Cargo manifest, in the format named by `Content-Type`",
+        content(
+            (String = "application/toml"),
+            (String = "application/yaml"),
+            (String = "application/json"),
+        ),
+    ),
+    responses(
+        (status = 200, description = "The same manifest, every field kept, in the format named by `Accept` (TOML by default)", content(
+            (String = "application/toml"),
+            (String = "application/yaml"),
+            (String = "application/json"),
+        )),
+        (status = 400, description = "Unparseable manifest", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 406, description = "None of the accepted formats is supported", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 415, description = "Unsupported content type", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 422, description = "Manifest cannot be written in the accepted format", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+)]
+pub async fn convert_cargo_manifest(
+    headers: HeaderMap,
+    body: String,
+) -> Result<impl IntoResponse, Day5AppError> {
+    let manifest = manifest_table(&headers, &body)?;
+    let format = ManifestFormat::accepted(&headers)?;
+
+    Ok((
+        [(CONTENT_TYPE, format.media_type())],
+        format.write(&manifest)?,
+    ))
+}
+
+/// The non-optional `[dependencies]`, with inherited entries filled in from
+/// `[workspace.dependencies]`.
+fn dependency_requests(manifest: &Manifest) -> Vec<DependencyRequest> {
+    let workspace_dependencies = manifest
+        .workspace
+        .as_ref()
+        .and_then(|workspace| workspace.dependencies.as_ref());
+
+    manifest
+        .dependencies
+        .iter()
+        .flatten()
+        .filter_map(|(name, dependency)| {
+            let (dependency, inherited_features) = match dependency {
+                // Optionality is the member's to declare, never the workspace's.
+                Dependency::Inherited(inherited) if inherited.optional == Some(true) => {
+                    return None;
+                }
+                Dependency::Inherited(inherited) => (
+                    workspace_dependencies?.get(name)?,
+                    inherited.features.clone().unwrap_or_default(),
+                ),
+                dependency => (dependency, Vec::new()),
+            };
+
+            match dependency {
+                Dependency::Simple(version) => Some(DependencyRequest {
+                    name: name.clone(),
+                    req: version.clone(),
+                    features: inherited_features,
+                    default_features: true,
+                }),
+                Dependency::Detailed(detail) if detail.optional != Some(true) => {
+                    Some(DependencyRequest {
+                        name: detail.package.clone().unwrap_or_else(|| name.clone()),
+                        req: detail.version.clone().unwrap_or_else(|| String::from("*")),
+                        features: detail
+                            .features
+                            .iter()
+                            .flatten()
+                            .chain(&inherited_features)
+                            .cloned()
+                            .collect(),
+                        default_features: detail.default_features.unwrap_or(true),
+                    })
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Resolved dependency tree of an uploaded manifest.
+///
+/// The tree form of `/5/manifest`. That route takes the raw manifest as its
+/// body and answers with the order summary, so the optional Cargo.lock needs
+/// a multipart upload of its own. Without a `lockfile` part, versions are
+/// resolved against the configured registry index snapshot. Duplicate
+/// versions, yanked entries and the features enabled per crate are marked.
+#[utoipa::path(
+    post,
+    path = "/5/manifest/tree",
+    tag = "dec5",
+    request_body(
+        content = String,
+        description = "Form with a `manifest` field, read as TOML unless its content type names YAML or JSON, and an optional `lockfile` field holding a Cargo.lock",
+        content_type = "multipart/form-data",
+    ),
+    responses(
+        (status = 200, description = "Resolved tree of the manifest's dependencies", body = DependencyTree),
+        (status = 400, description = "Missing or unparseable manifest or lockfile", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "No lockfile uploaded and no registry index configured", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Registry index could not be read, or resolution did not finish", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+)]
+pub async fn dependency_tree(
+    State(state): State<Arc<AppState>>,
+    Multipart(mut multipart): Multipart,
+) -> Result<Json<DependencyTree>, Day5AppError> {
+    let mut manifest = None;
+    let mut lockfile = None;
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|_| Day5AppError::InvalidUpload)?
+    {
+        match field.name() {
+            Some("manifest") => {
+                let format = field
+                    .content_type()
+                    .and_then(ManifestFormat::from_media_type)
+                    .unwrap_or(ManifestFormat::Toml);
+                let content = field
+                    .text()
+                    .await
+                    .map_err(|_| Day5AppError::InvalidUpload)?;
+                manifest = Some(format.read(&content)?);
+            }
+            Some("lockfile") => {
+                let content = field
+                    .text()
+                    .await
+                    .map_err(|_| Day5AppError::InvalidUpload)?;
+                lockfile = Some(
+                    toml::from_str::<Lockfile>(&content).map_err(Day5AppError::InvalidLockfile)?,
+                );
+            }
+            _ => {}
+        }
+    }
+
+    let manifest = manifest.ok_or(Day5AppError::InvalidUpload)?;
+    if is_lockfile_table(&manifest) {
+        return Err(Day5AppError::Lockfile);
+    }
+    let manifest = Manifest::from_str(
+        &toml::to_string(&manifest).map_err(|e| Day5AppError::ConversionError(e.to_string()))?,
+    )?;
+    let root = manifest
+        .package
+        .as_ref()
+        .map(|package| package.name.clone())
+        .ok_or(Day5AppError::NotAPackage)?;
+    let requests = dependency_requests(&manifest);
+
+    if lockfile.is_none() && state.registry_index.is_none() {
+        return Err(Day5AppError::NoResolutionSource);
+    }
+    let index = state.registry_index.clone();
+
+    // The index is read from disk, possibly many files deep.
+    let tree = tokio::task::spawn_blocking(move || {
+        Resolver::new(lockfile.as_ref(), index.as_ref()).resolve(&root, &requests)
+    })
+    .await
+    .map_err(Day5AppError::ResolutionAborted)?
+    .map_err(Day5AppError::IndexUnreadable)?;
+
+    Ok(Json(tree))
 }
 
 pub enum Day5AppError {
     UnsupportedMediaType,
+    NotAcceptable,
     CargoManifestError(cargo_manifest::Error),
     YAMLManifestError(serde_yaml::Error),
     JSONManifestError(serde_json::Error),
-    ManifestError,
-    NoMagicKeyword,
+    Lockfile,
+    NotAPackage,
+    RuleFailed(ManifestRule, String),
     NoContent,
     TomlParseError(toml::de::Error),
+    InvalidToml(toml::de::Error),
+    ConversionError(String),
+    InvalidUpload,
+    InvalidLockfile(toml::de::Error),
+    NoResolutionSource,
+    IndexUnreadable(io::Error),
+    ResolutionAborted(tokio::task::JoinError),
 }
 
-impl IntoResponse for Day5AppError {
-    fn into_response(self) -> Response {
+impl From<Day5AppError> for AppError {
+    fn from(error: Day5AppError) -> Self {
         const INVALID_MANIFEST_DETAIL: &str = "Invalid manifest";
 
-        match self {
-            Day5AppError::UnsupportedMediaType => (StatusCode::UNSUPPORTED_MEDIA_TYPE, ""),
+        match error {
+            Day5AppError::UnsupportedMediaType => AppError::new(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "manifest.unsupported_media_type",
+                "Expected application/toml, application/yaml or application/json",
+            ),
+            Day5AppError::NotAcceptable => AppError::new(
+                StatusCode::NOT_ACCEPTABLE,
+                "manifest.not_acceptable",
+                "Can answer with application/toml, application/yaml or application/json",
+            ),
             Day5AppError::CargoManifestError(rejection) => {
-                println!("{}", rejection);
-                (StatusCode::BAD_REQUEST, INVALID_MANIFEST_DETAIL)
+                tracing::warn!(error = %rejection, "invalid cargo manifest");
+                AppError::new(
+                    StatusCode::BAD_REQUEST,
+                    "manifest.invalid",
+                    INVALID_MANIFEST_DETAIL,
+                )
             }
             Day5AppError::YAMLManifestError(rejection) => {
-                println!("{}", rejection);
-                (StatusCode::BAD_REQUEST, INVALID_MANIFEST_DETAIL)
+                tracing::warn!(error = %rejection, "invalid yaml manifest");
+                AppError::new(
+                    StatusCode::BAD_REQUEST,
+                    "manifest.invalid",
+                    INVALID_MANIFEST_DETAIL,
+                )
             }
             Day5AppError::JSONManifestError(rejection) => {
-                println!("{}", rejection);
-                (StatusCode::BAD_REQUEST, INVALID_MANIFEST_DETAIL)
+                tracing::warn!(error = %rejection, "invalid json manifest");
+                AppError::new(
+                    StatusCode::BAD_REQUEST,
+                    "manifest.invalid",
+                    INVALID_MANIFEST_DETAIL,
+                )
+            }
+            Day5AppError::Lockfile => AppError::new(
+                StatusCode::BAD_REQUEST,
+                "manifest.lockfile",
+                "Got a Cargo.lock where a Cargo.toml was expected",
+            ),
+            Day5AppError::NotAPackage => AppError::new(
+                StatusCode::BAD_REQUEST,
+                "manifest.not_a_package",
+                "Manifest has neither a [package] nor a [workspace] table",
+            ),
+            Day5AppError::RuleFailed(rule, detail) => AppError::new(
+                StatusCode::BAD_REQUEST,
+                rule.code(),
+                format!("Rule {} failed: {detail}", rule.name()),
+            ),
+            Day5AppError::NoContent => {
+                AppError::new(StatusCode::NO_CONTENT, "manifest.no_orders", "")
+            }
+            Day5AppError::InvalidToml(rejection) => {
+                tracing::warn!(error = %rejection, "invalid toml manifest");
+                AppError::new(
+                    StatusCode::BAD_REQUEST,
+                    "manifest.invalid",
+                    INVALID_MANIFEST_DETAIL,
+                )
+            }
+            Day5AppError::ConversionError(detail) => AppError::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "manifest.unconvertible",
+                detail,
+            ),
+            Day5AppError::InvalidUpload => AppError::new(
+                StatusCode::BAD_REQUEST,
+                "manifest.invalid_upload",
+                "Upload the manifest in the manifest field of a multipart form",
+            ),
+            Day5AppError::InvalidLockfile(rejection) => {
+                tracing::warn!(error = %rejection, "invalid lockfile");
+                AppError::new(
+                    StatusCode::BAD_REQUEST,
+                    "manifest.invalid_lockfile",
+                    "Got an invalid Cargo.lock",
+                )
+            }
+            Day5AppError::NoResolutionSource => AppError::new(
+                StatusCode::CONFLICT,
+                "manifest.no_resolution_source",
+                "Upload a Cargo.lock, no registry index is configured to resolve against",
+            ),
+            Day5AppError::IndexUnreadable(error) => {
+                tracing::error!(%error, "registry index could not be read");
+                AppError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "manifest.index_unreadable",
+                    "The registry index could not be read",
+                )
+            }
+            Day5AppError::ResolutionAborted(error) => {
+                tracing::error!(%error, "dependency resolution did not finish");
+                AppError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "manifest.resolution_aborted",
+                    "Dependency resolution did not finish",
+                )
             }
-            Day5AppError::ManifestError => (StatusCode::BAD_REQUEST, INVALID_MANIFEST_DETAIL),
-            Day5AppError::NoMagicKeyword => (StatusCode::BAD_REQUEST, "Magic keyword not provided"),
-            Day5AppError::NoContent => (StatusCode::NO_CONTENT, ""),
             Day5AppError::TomlParseError(rejection) => {
-                println!("{}", rejection);
-                (StatusCode::NO_CONTENT, "")
+                tracing::warn!(error = %rejection, "manifest orders could not be parsed");
+                AppError::new(StatusCode::NO_CONTENT, "manifest.no_orders", "")
             }
         }
-        .into_response()
+    }
+}
+
+impl IntoResponse for Day5AppError {
+    fn into_response(self) -> Response {
+        AppError::from(self).into_response()
     }
 }
 
@@ -8,11 +8,15 @@ use axum::{
 };
 use serde::Deserialize;
 use serde_json::{json, Value};
+use utoipa::ToSchema;
 
-use crate::AppState;
+use crate::{
+    error::{AppError, ProblemDetails},
+    AppState,
+};
 
 #[allow(non_camel_case_types)]
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub enum ConversionPayload {
     liters(f32),
     gallons(f32),
@@ -37,6 +41,20 @@ fn convert_unit(unit: ConversionPayload) -> Value {
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/9/milk",
+    tag = "dec9",
+    request_body(
+        content = Option<ConversionPayload>,
+        description = "Unit conversion, only read when sent as application/json",
+    ),
+    responses(
+        (status = 200, description = "Milk withdrawn, or the converted unit as JSON", body = String, content_type = "text/plain"),
+        (status = 400, description = "Invalid conversion payload", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 429, description = "Bucket is empty", body = String, content_type = "text/plain"),
+    ),
+)]
 pub async fn milk_bucket_leaky(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
@@ -63,6 +81,12 @@ pub async fn milk_bucket_leaky(
     Ok((StatusCode::OK, String::from("Milk withdrawn\n")))
 }
 
+#[utoipa::path(
+    post,
+    path = "/9/refill",
+    tag = "dec9",
+    responses((status = 200, description = "Bucket refilled")),
+)]
 pub async fn milk_bucket_refill(State(state): State<Arc<AppState>>) -> impl IntoResponse {
     state.leaky_milk_bucket.refill().await;
 
@@ -73,15 +97,24 @@ pub enum Day9AppError {
     JSONError(serde_json::Error),
 }
 
-impl IntoResponse for Day9AppError {
-    fn into_response(self) -> Response {
-        match self {
+impl From<Day9AppError> for AppError {
+    fn from(error: Day9AppError) -> Self {
+        match error {
             Day9AppError::JSONError(rejection) => {
-                println!("{}", rejection);
-                (StatusCode::BAD_REQUEST, "")
+                tracing::warn!(error = %rejection, "invalid milk conversion payload");
+                AppError::new(
+                    StatusCode::BAD_REQUEST,
+                    "milk.invalid_conversion",
+                    rejection.to_string(),
+                )
             }
         }
-        .into_response()
+    }
+}
+
+impl IntoResponse for Day9AppError {
+    fn into_response(self) -> Response {
+        AppError::from(self).into_response()
     }
 }
 
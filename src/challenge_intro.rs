@@ -3,10 +3,22 @@ use axum::{
     response::IntoResponse,
 };
 
+#[utoipa::path(
+    get,
+    path = "/",
+    tag = "intro",
+    responses((status = 200, description = "Greeting", body = String, content_type = "text/plain")),
+)]
 pub async fn hello_bird() -> &'static str {
     "Hello, bird!"
 }
 
+#[utoipa::path(
+    get,
+    path = "/-1/seek",
+    tag = "intro",
+    responses((status = 302, description = "Redirect to the hidden video")),
+)]
 pub async fn seek_and_find() -> impl IntoResponse {
     (
         StatusCode::FOUND,
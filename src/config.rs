@@ -0,0 +1,341 @@
+use std::{
+    fmt::Display,
+    fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use anyhow::Context;
+use serde::Deserialize;
+
+use crate::modules::ChallengeModule;
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub bind_address: SocketAddr,
+    pub database_url: Option<String>,
+    pub milk_bucket: MilkBucketConfig,
+    pub board_size: usize,
+    pub log: LogConfig,
+    /// How long in-flight requests may take to finish after a shutdown signal.
+    pub shutdown_timeout_secs: u64,
+    /// Challenge modules that start switched off; they answer 503 until
+    /// re-enabled through the admin endpoint.
+    pub disabled_modules: Vec<ChallengeModule>,
+    /// Bearer token for `/admin/*`. The admin endpoints refuse every request
+    /// while this is unset.
+    pub admin_token: Option<String>,
+    /// Secret behind `/2/anonymize` and `/2/deanonymize`, which answer 503
+    /// while this is unset.
+    pub anonymization_secret: Option<String>,
+    pub manifest_rules: ManifestRules,
+    /// Registry index snapshot `/5/manifest/tree` resolves against when no
+    /// Cargo.lock is uploaded. It also provides yanked flags and features.
+    pub registry_index: Option<PathBuf>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct LogConfig {
+    /// `EnvFilter` directive, e.g. `info` or `shuttlings_cch24=debug,tower_http=info`.
+    pub level: String,
+    pub format: LogFormat,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Pretty,
+    Json,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct MilkBucketConfig {
+    pub max: u32,
+    pub tokens: u32,
+    pub refill_interval_secs: u64,
+    pub refill_amount: u32,
+}
+
+/// Checks `/5/manifest` applies before reading the orders.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ManifestRules {
+    /// Keywords the manifest must list, all of them.
+    pub required_keywords: Vec<String>,
+    /// SPDX identifiers `license` may use. Empty accepts any license.
+    pub allowed_licenses: Vec<String>,
+    pub min_rust_version: Option<RustVersion>,
+    pub max_rust_version: Option<RustVersion>,
+    /// Keys `[package.metadata]` or `[workspace.metadata]` must have.
+    pub required_metadata_keys: Vec<String>,
+}
+
+/// A `rust-version` value: two or three numeric components, e.g. `1.70`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RustVersion {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            bind_address: SocketAddr::from(([0, 0, 0, 0], 8000)),
+            database_url: None,
+            milk_bucket: MilkBucketConfig::default(),
+            board_size: 4,
+            log: LogConfig::default(),
+            shutdown_timeout_secs: 30,
+            disabled_modules: Vec::new(),
+            admin_token: None,
+            anonymization_secret: None,
+            manifest_rules: ManifestRules::default(),
+            registry_index: None,
+        }
+    }
+}
+
+impl Default for ManifestRules {
+    fn default() -> Self {
+        ManifestRules {
+            required_keywords: vec![String::from("Christmas 2024")],
+            allowed_licenses: Vec::new(),
+            min_rust_version: None,
+            max_rust_version: None,
+            required_metadata_keys: Vec::new(),
+        }
+    }
+}
+
+impl FromStr for RustVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid rust-version {s:?}, expected e.g. 1, 1.70 or 1.70.0");
+
+        let components = s
+            .split('.')
+            .map(|component| {
+                if component.is_empty()
+                    || !component.chars().all(|char| char.is_ascii_digit())
+                    || (component.len() > 1 && component.starts_with('0'))
+                {
+                    return Err(invalid());
+                }
+                component.parse::<u64>().map_err(|_| invalid())
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        match components[..] {
+            [major] => Ok(RustVersion {
+                major,
+                minor: 0,
+                patch: 0,
+            }),
+            [major, minor] => Ok(RustVersion {
+                major,
+                minor,
+                patch: 0,
+            }),
+            [major, minor, patch] => Ok(RustVersion {
+                major,
+                minor,
+                patch,
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl Display for RustVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl<'de> Deserialize<'de> for RustVersion {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig {
+            level: String::from("info"),
+            format: LogFormat::Pretty,
+        }
+    }
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pretty" => Ok(LogFormat::Pretty),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("unknown log format {s:?}, expected pretty or json")),
+        }
+    }
+}
+
+impl Default for MilkBucketConfig {
+    fn default() -> Self {
+        MilkBucketConfig {
+            max: 5,
+            tokens: 5,
+            refill_interval_secs: 1,
+            refill_amount: 1,
+        }
+    }
+}
+
+impl MilkBucketConfig {
+    pub fn refill_interval(&self) -> Duration {
+        Duration::from_secs(self.refill_interval_secs)
+    }
+}
+
+impl ServerConfig {
+    pub fn shutdown_timeout(&self) -> Duration {
+        Duration::from_secs(self.shutdown_timeout_secs)
+    }
+
+    /// Reads the optional TOML file at `path`, then applies `CCH24_*` and
+    /// `DATABASE_URL` environment overrides on top of it.
+    pub fn load(path: Option<&Path>) -> anyhow::Result<Self> {
+        ServerConfig::load_with(path, |name| std::env::var(name).ok())
+    }
+
+    /// Like [`ServerConfig::load`], but looks the overrides up through `var`,
+    /// e.g. in Shuttle's secrets before the environment.
+    pub fn load_with(
+        path: Option<&Path>,
+        var: impl Fn(&str) -> Option<String>,
+    ) -> anyhow::Result<Self> {
+        let mut config = match path {
+            Some(path) => {
+                let contents = fs::read_to_string(path)
+                    .with_context(|| format!("reading config file {}", path.display()))?;
+                toml::from_str(&contents)
+                    .with_context(|| format!("parsing config file {}", path.display()))?
+            }
+            None => ServerConfig::default(),
+        };
+
+        env_override(&var, "CCH24_BIND_ADDRESS", &mut config.bind_address)?;
+        env_override(&var, "CCH24_BOARD_SIZE", &mut config.board_size)?;
+        env_override(&var, "CCH24_MILK_BUCKET_MAX", &mut config.milk_bucket.max)?;
+        env_override(
+            &var,
+            "CCH24_MILK_BUCKET_TOKENS",
+            &mut config.milk_bucket.tokens,
+        )?;
+        env_override(
+            &var,
+            "CCH24_MILK_BUCKET_REFILL_INTERVAL_SECS",
+            &mut config.milk_bucket.refill_interval_secs,
+        )?;
+        env_override(
+            &var,
+            "CCH24_MILK_BUCKET_REFILL_AMOUNT",
+            &mut config.milk_bucket.refill_amount,
+        )?;
+        env_override(&var, "CCH24_LOG_LEVEL", &mut config.log.level)?;
+        env_override(&var, "CCH24_LOG_FORMAT", &mut config.log.format)?;
+        env_override(
+            &var,
+            "CCH24_SHUTDOWN_TIMEOUT_SECS",
+            &mut config.shutdown_timeout_secs,
+        )?;
+        if let Some(disabled_modules) = var("CCH24_DISABLED_MODULES") {
+            config.disabled_modules = disabled_modules
+                .split(',')
+                .map(str::trim)
+                .filter(|module| !module.is_empty())
+                .map(str::parse)
+                .collect::<Result<_, _>>()
+                .map_err(|e| anyhow::anyhow!("invalid value for CCH24_DISABLED_MODULES: {e}"))?;
+        }
+        if let Some(admin_token) = var("CCH24_ADMIN_TOKEN") {
+            config.admin_token = Some(admin_token);
+        }
+        if let Some(anonymization_secret) = var("CCH24_ANONYMIZATION_SECRET") {
+            config.anonymization_secret = Some(anonymization_secret);
+        }
+        env_list(
+            &var,
+            "CCH24_MANIFEST_REQUIRED_KEYWORDS",
+            &mut config.manifest_rules.required_keywords,
+        );
+        env_list(
+            &var,
+            "CCH24_MANIFEST_ALLOWED_LICENSES",
+            &mut config.manifest_rules.allowed_licenses,
+        );
+        env_list(
+            &var,
+            "CCH24_MANIFEST_REQUIRED_METADATA_KEYS",
+            &mut config.manifest_rules.required_metadata_keys,
+        );
+        if let Some(version) = var("CCH24_MANIFEST_MIN_RUST_VERSION") {
+            config.manifest_rules.min_rust_version = Some(version.parse().map_err(|e| {
+                anyhow::anyhow!("invalid value for CCH24_MANIFEST_MIN_RUST_VERSION: {e}")
+            })?);
+        }
+        if let Some(version) = var("CCH24_MANIFEST_MAX_RUST_VERSION") {
+            config.manifest_rules.max_rust_version = Some(version.parse().map_err(|e| {
+                anyhow::anyhow!("invalid value for CCH24_MANIFEST_MAX_RUST_VERSION: {e}")
+            })?);
+        }
+        if let Some(registry_index) = var("CCH24_REGISTRY_INDEX") {
+            config.registry_index = Some(PathBuf::from(registry_index));
+        }
+        if let Some(database_url) = var("DATABASE_URL") {
+            config.database_url = Some(database_url);
+        }
+
+        Ok(config)
+    }
+}
+
+fn env_override<T>(
+    var: impl Fn(&str) -> Option<String>,
+    name: &str,
+    target: &mut T,
+) -> anyhow::Result<()>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    if let Some(value) = var(name) {
+        *target = value
+            .parse()
+            .map_err(|e| anyhow::anyhow!("invalid value for {name}: {e}"))?;
+    }
+    Ok(())
+}
+
+/// Replaces `target` with the comma separated values of `name`, if set.
+fn env_list(var: impl Fn(&str) -> Option<String>, name: &str, target: &mut Vec<String>) {
+    if let Some(values) = var(name) {
+        *target = values
+            .split(',')
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(String::from)
+            .collect();
+    }
+}
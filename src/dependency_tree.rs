@@ -0,0 +1,590 @@
+//! Resolves a manifest's `[dependencies]` against an uploaded `Cargo.lock`
+//! or a local snapshot of a registry index, laid out like crates.io's.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Deserialize)]
+pub struct Lockfile {
+    #[serde(default)]
+    package: Vec<LockedPackage>,
+}
+
+#[derive(Deserialize)]
+struct LockedPackage {
+    name: String,
+    version: Version,
+    source: Option<String>,
+    /// `name`, `name version` or `name version (source)`.
+    #[serde(default)]
+    dependencies: Vec<String>,
+}
+
+impl Lockfile {
+    fn find(&self, name: &str, version: Option<&Version>) -> Option<&LockedPackage> {
+        self.package
+            .iter()
+            .filter(|package| package.name == name)
+            .find(|package| version.map_or(true, |version| &package.version == version))
+    }
+
+    /// The highest locked version of `name` matching `req`.
+    fn select(&self, name: &str, req: &VersionReq) -> Option<&LockedPackage> {
+        self.package
+            .iter()
+            .filter(|package| package.name == name && req.matches(&package.version))
+            .max_by(|a, b| a.version.cmp(&b.version))
+    }
+}
+
+/// One line of an index file, describing one published version.
+#[derive(Deserialize)]
+struct IndexVersion {
+    vers: Version,
+    #[serde(default)]
+    deps: Vec<IndexDependency>,
+    #[serde(default)]
+    features: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    features2: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    yanked: bool,
+}
+
+#[derive(Deserialize)]
+struct IndexDependency {
+    /// The name the dependent uses, which differs from the crate's when
+    /// `package` is set.
+    name: String,
+    req: String,
+    #[serde(default)]
+    features: Vec<String>,
+    #[serde(default)]
+    optional: bool,
+    #[serde(default = "default_true")]
+    default_features: bool,
+    kind: Option<String>,
+    package: Option<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl IndexVersion {
+    fn feature_table(&self) -> impl Iterator<Item = (&String, &Vec<String>)> {
+        self.features.iter().chain(&self.features2)
+    }
+}
+
+impl IndexDependency {
+    fn crate_name(&self) -> &str {
+        self.package.as_deref().unwrap_or(&self.name)
+    }
+
+    fn is_dev(&self) -> bool {
+        self.kind.as_deref() == Some("dev")
+    }
+}
+
+/// A directory holding an index snapshot, e.g. a checkout of
+/// `crates.io-index`.
+#[derive(Clone)]
+pub struct RegistryIndex {
+    root: PathBuf,
+}
+
+impl RegistryIndex {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        RegistryIndex { root: root.into() }
+    }
+
+    /// `1/a`, `2/ab`, `3/a/abc` and `ab/cd/abcd…`, as laid out by cargo.
+    /// `None` for a name that is not a valid crate name, so it can neither
+    /// split mid-character nor reach outside the index.
+    fn entry_path(&self, name: &str) -> Option<PathBuf> {
+        if !is_valid_crate_name(name) {
+            return None;
+        }
+
+        let name = name.to_ascii_lowercase();
+        let relative = match name.len() {
+            1 => Path::new("1").join(&name),
+            2 => Path::new("2").join(&name),
+            3 => Path::new("3").join(&name[..1]).join(&name),
+            _ => Path::new(&name[..2]).join(&name[2..4]).join(&name),
+        };
+        Some(self.root.join(relative))
+    }
+
+    fn versions(&self, name: &str) -> io::Result<Vec<IndexVersion>> {
+        let Some(path) = self.entry_path(name) else {
+            return Ok(Vec::new());
+        };
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(error),
+        };
+
+        contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).map_err(io::Error::other))
+            .collect()
+    }
+}
+
+/// Longest crate name crates.io accepts.
+const MAX_CRATE_NAME_LEN: usize = 64;
+
+/// Non-empty, at most [`MAX_CRATE_NAME_LEN`] long and made of ASCII letters,
+/// digits, `-` and `_`.
+fn is_valid_crate_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_CRATE_NAME_LEN
+        && name
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_'))
+}
+
+/// A dependency as the manifest declares it.
+pub struct DependencyRequest {
+    pub name: String,
+    pub req: String,
+    pub features: Vec<String>,
+    pub default_features: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct DependencyTree {
+    /// Package name of the manifest.
+    root: String,
+    dependencies: Vec<DependencyNode>,
+    /// Dependencies nothing could be found for.
+    unresolved: Vec<UnresolvedDependency>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct DependencyNode {
+    name: String,
+    #[schema(value_type = String)]
+    version: Version,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<String>,
+    /// Features enabled on this crate by everything that depends on it.
+    features: Vec<String>,
+    /// Another version of the same crate is in the tree too.
+    duplicate: bool,
+    /// The index snapshot lists this version as yanked.
+    yanked: bool,
+    /// Already expanded earlier in the tree, so its dependencies are left out.
+    repeated: bool,
+    #[schema(no_recursion)]
+    dependencies: Vec<DependencyNode>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct UnresolvedDependency {
+    name: String,
+    requirement: String,
+    /// The crate that asked for it, `name version`, or the manifest's package.
+    required_by: String,
+    reason: String,
+}
+
+type CrateId = (String, Version);
+
+#[derive(Default)]
+struct ResolvedCrate {
+    source: Option<String>,
+    yanked: bool,
+    /// Features requested by dependents, before expanding the feature table.
+    requested: BTreeSet<String>,
+    features: BTreeSet<String>,
+    dependencies: BTreeSet<CrateId>,
+}
+
+pub struct Resolver<'a> {
+    lockfile: Option<&'a Lockfile>,
+    index: Option<&'a RegistryIndex>,
+    index_cache: HashMap<String, Vec<IndexVersion>>,
+    crates: BTreeMap<CrateId, ResolvedCrate>,
+    queue: VecDeque<CrateId>,
+    unresolved: Vec<UnresolvedDependency>,
+}
+
+impl<'a> Resolver<'a> {
+    /// Resolves through the lockfile when there is one, otherwise through
+    /// the index, which also supplies yanked flags and feature tables.
+    pub fn new(lockfile: Option<&'a Lockfile>, index: Option<&'a RegistryIndex>) -> Self {
+        Resolver {
+            lockfile,
+            index,
+            index_cache: HashMap::new(),
+            crates: BTreeMap::new(),
+            queue: VecDeque::new(),
+            unresolved: Vec::new(),
+        }
+    }
+
+    pub fn resolve(
+        mut self,
+        root: &str,
+        requests: &[DependencyRequest],
+    ) -> io::Result<DependencyTree> {
+        let mut direct = Vec::new();
+        for request in requests {
+            if let Some(id) = self.request(root, request)? {
+                direct.push(id);
+            }
+        }
+
+        while let Some(id) = self.queue.pop_front() {
+            self.expand(id)?;
+        }
+
+        let mut versions = HashMap::<&str, usize>::new();
+        for (name, _) in self.crates.keys() {
+            *versions.entry(name).or_default() += 1;
+        }
+        let duplicates = versions
+            .into_iter()
+            .filter(|(_, count)| *count > 1)
+            .map(|(name, _)| name.to_string())
+            .collect::<HashSet<_>>();
+
+        let mut shown = HashSet::new();
+        let dependencies = direct
+            .iter()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|id| self.node(id, &duplicates, &mut shown))
+            .collect();
+
+        Ok(DependencyTree {
+            root: root.to_string(),
+            dependencies,
+            unresolved: self.unresolved,
+        })
+    }
+
+    fn index_versions(&mut self, name: &str) -> io::Result<&[IndexVersion]> {
+        let Some(index) = self.index else {
+            return Ok(&[]);
+        };
+        if !self.index_cache.contains_key(name) {
+            let versions = index.versions(name)?;
+            self.index_cache.insert(name.to_string(), versions);
+        }
+        Ok(&self.index_cache[name])
+    }
+
+    fn index_version(&mut self, (name, version): &CrateId) -> io::Result<Option<&IndexVersion>> {
+        Ok(self
+            .index_versions(name)?
+            .iter()
+            .find(|candidate| &candidate.vers == version))
+    }
+
+    /// Picks the version `request` resolves to and enables the requested
+    /// features on it.
+    fn request(
+        &mut self,
+        required_by: &str,
+        request: &DependencyRequest,
+    ) -> io::Result<Option<CrateId>> {
+        let unresolved = |reason: &str| UnresolvedDependency {
+            name: request.name.clone(),
+            requirement: request.req.clone(),
+            required_by: required_by.to_string(),
+            reason: reason.to_string(),
+        };
+
+        if !is_valid_crate_name(&request.name) {
+            self.unresolved.push(unresolved("invalid crate name"));
+            return Ok(None);
+        }
+
+        let Ok(req) = VersionReq::parse(&request.req) else {
+            self.unresolved
+                .push(unresolved("invalid version requirement"));
+            return Ok(None);
+        };
+
+        let selected = match self.lockfile {
+            Some(lockfile) => match lockfile.select(&request.name, &req) {
+                Some(package) => Some((package.version.clone(), package.source.clone())),
+                None => {
+                    self.unresolved.push(unresolved("not in the lockfile"));
+                    return Ok(None);
+                }
+            },
+            None => {
+                let candidates = self.index_versions(&request.name)?;
+                let matching = candidates
+                    .iter()
+                    .filter(|candidate| req.matches(&candidate.vers));
+                match matching
+                    .filter(|candidate| !candidate.yanked)
+                    .max_by(|a, b| a.vers.cmp(&b.vers))
+                {
+                    Some(candidate) => Some((candidate.vers.clone(), None)),
+                    None if candidates.iter().any(|c| req.matches(&c.vers)) => {
+                        self.unresolved
+                            .push(unresolved("only yanked versions match"));
+                        return Ok(None);
+                    }
+                    None => None,
+                }
+            }
+        };
+        let Some((version, source)) = selected else {
+            self.unresolved.push(unresolved("not in the index"));
+            return Ok(None);
+        };
+
+        let id = (request.name.clone(), version);
+        self.enable(&id, source, request)?;
+        Ok(Some(id))
+    }
+
+    /// Records the features `request` asks for, queueing the crate again when
+    /// that enables anything new.
+    fn enable(
+        &mut self,
+        id: &CrateId,
+        source: Option<String>,
+        request: &DependencyRequest,
+    ) -> io::Result<()> {
+        let is_new = !self.crates.contains_key(id);
+        let yanked = self
+            .index_version(id)?
+            .is_some_and(|version| version.yanked);
+
+        let resolved = self.crates.entry(id.clone()).or_default();
+        if is_new {
+            resolved.source = source;
+            resolved.yanked = yanked;
+        }
+
+        let before = resolved.requested.len();
+        resolved.requested.extend(request.features.iter().cloned());
+        if request.default_features {
+            resolved.requested.insert(String::from("default"));
+        }
+
+        if is_new || resolved.requested.len() != before {
+            self.queue.push_back(id.clone());
+        }
+        Ok(())
+    }
+
+    /// Expands the crate's enabled features and resolves its dependencies.
+    fn expand(&mut self, id: CrateId) -> io::Result<()> {
+        let requested = self.crates[&id].requested.clone();
+        let (features, requests) = match self.index_version(&id)? {
+            Some(version) => {
+                let features = enabled_features(version, &requested);
+                let requests = dependency_requests(version, &features);
+                (features, Some(requests))
+            }
+            // Without a feature table only the explicitly requested
+            // features are known.
+            None => (
+                requested
+                    .into_iter()
+                    .filter(|feature| feature != "default")
+                    .collect(),
+                None,
+            ),
+        };
+        self.crates.get_mut(&id).unwrap().features = features;
+
+        let required_by = format!("{} {}", id.0, id.1);
+        let children = match self.lockfile {
+            Some(lockfile) => self.locked_dependencies(lockfile, &id, requests)?,
+            None => {
+                let mut children = Vec::new();
+                for request in requests.unwrap_or_default() {
+                    if let Some(child) = self.request(&required_by, &request)? {
+                        children.push(child);
+                    }
+                }
+                children
+            }
+        };
+
+        self.crates
+            .get_mut(&id)
+            .unwrap()
+            .dependencies
+            .extend(children);
+        Ok(())
+    }
+
+    /// The lockfile's dependencies of `id`, with the features the index says
+    /// the crate asks for when the index knows it.
+    fn locked_dependencies(
+        &mut self,
+        lockfile: &'a Lockfile,
+        id: &CrateId,
+        requests: Option<Vec<DependencyRequest>>,
+    ) -> io::Result<Vec<CrateId>> {
+        let Some(package) = lockfile.find(&id.0, Some(&id.1)) else {
+            return Ok(Vec::new());
+        };
+
+        let mut children = Vec::new();
+        for dependency in &package.dependencies {
+            let mut parts = dependency.split_whitespace();
+            let name = parts.next().unwrap_or_default();
+            let version = parts.next().and_then(|version| version.parse().ok());
+            let Some(locked) = lockfile.find(name, version.as_ref()) else {
+                continue;
+            };
+
+            let request = requests
+                .iter()
+                .flatten()
+                .find(|request| request.name == name);
+            let request = DependencyRequest {
+                name: name.to_string(),
+                req: String::from("*"),
+                features: request
+                    .map(|request| request.features.clone())
+                    .unwrap_or_default(),
+                default_features: request.map_or(true, |request| request.default_features),
+            };
+
+            let child = (locked.name.clone(), locked.version.clone());
+            self.enable(&child, locked.source.clone(), &request)?;
+            children.push(child);
+        }
+        Ok(children)
+    }
+
+    fn node(
+        &self,
+        id: &CrateId,
+        duplicates: &HashSet<String>,
+        shown: &mut HashSet<CrateId>,
+    ) -> DependencyNode {
+        let resolved = &self.crates[id];
+        let repeated = !shown.insert(id.clone());
+
+        DependencyNode {
+            name: id.0.clone(),
+            version: id.1.clone(),
+            source: resolved.source.clone(),
+            features: resolved.features.iter().cloned().collect(),
+            duplicate: duplicates.contains(&id.0),
+            yanked: resolved.yanked,
+            repeated,
+            dependencies: if repeated {
+                Vec::new()
+            } else {
+                resolved
+                    .dependencies
+                    .iter()
+                    .map(|child| self.node(child, duplicates, shown))
+                    .collect()
+            },
+        }
+    }
+}
+
+/// `requested` plus every feature those enable in turn. `default` only
+/// counts when the crate defines it.
+fn enabled_features(version: &IndexVersion, requested: &BTreeSet<String>) -> BTreeSet<String> {
+    let table = version.feature_table().collect::<HashMap<_, _>>();
+    let mut enabled = BTreeSet::new();
+    let mut pending = requested.iter().cloned().collect::<Vec<_>>();
+
+    while let Some(feature) = pending.pop() {
+        if feature == "default" && !table.contains_key(&feature) {
+            continue;
+        }
+        // Feature tables can be cyclic, e.g. `a = ["b"]` and `b = ["a"]`.
+        if !enabled.insert(feature.clone()) {
+            continue;
+        }
+        if let Some(implied) = table.get(&feature) {
+            pending.extend(
+                implied
+                    .iter()
+                    .filter(|implied| !implied.contains('/') && !implied.starts_with("dep:"))
+                    .cloned(),
+            );
+        }
+    }
+
+    enabled
+}
+
+/// The normal and build dependencies `features` turn on, including the
+/// features they in turn get through `dep/feature` entries.
+fn dependency_requests(
+    version: &IndexVersion,
+    features: &BTreeSet<String>,
+) -> Vec<DependencyRequest> {
+    let table = version.feature_table().collect::<HashMap<_, _>>();
+    let mut optional_enabled = HashSet::new();
+    let mut dependency_features = HashMap::<&str, Vec<String>>::new();
+
+    for feature in features {
+        // An optional dependency doubles as a feature of the same name
+        // unless the table defines it.
+        if !table.contains_key(feature) {
+            optional_enabled.insert(feature.as_str());
+        }
+        for implied in table.get(feature).into_iter().flatten() {
+            if let Some(dependency) = implied.strip_prefix("dep:") {
+                optional_enabled.insert(dependency);
+            } else if let Some((dependency, feature)) = implied.split_once('/') {
+                let weak = dependency.ends_with('?');
+                let dependency = dependency.trim_end_matches('?');
+                if !weak {
+                    optional_enabled.insert(dependency);
+                }
+                dependency_features
+                    .entry(dependency)
+                    .or_default()
+                    .push(feature.to_string());
+            }
+        }
+    }
+
+    version
+        .deps
+        .iter()
+        .filter(|dependency| !dependency.is_dev())
+        .filter(|dependency| {
+            !dependency.optional || optional_enabled.contains(dependency.name.as_str())
+        })
+        .map(|dependency| DependencyRequest {
+            name: dependency.crate_name().to_string(),
+            req: dependency.req.clone(),
+            features: dependency
+                .features
+                .iter()
+                .cloned()
+                .chain(
+                    dependency_features
+                        .get(dependency.name.as_str())
+                        .into_iter()
+                        .flatten()
+                        .cloned(),
+                )
+                .collect(),
+            default_features: dependency.default_features,
+        })
+        .collect()
+}
@@ -0,0 +1,128 @@
+use axum::{
+    extract::rejection::{JsonRejection, MultipartRejection, PathRejection, QueryRejection},
+    http::{header::CONTENT_TYPE, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::request_id;
+
+/// Error shared by every route. Rendered as an RFC 7807
+/// `application/problem+json` body carrying a stable `code` and the id of
+/// the request that failed.
+#[derive(Debug)]
+pub struct AppError {
+    status: StatusCode,
+    code: &'static str,
+    detail: String,
+}
+
+/// Body of every error response.
+#[derive(Serialize, ToSchema)]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    problem_type: &'static str,
+    title: &'static str,
+    status: u16,
+    detail: String,
+    /// Stable, machine-readable error code
+    code: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+}
+
+impl AppError {
+    pub fn new(status: StatusCode, code: &'static str, detail: impl Into<String>) -> Self {
+        AppError {
+            status,
+            code,
+            detail: detail.into(),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn code(&self) -> &'static str {
+        self.code
+    }
+
+    pub fn detail(&self) -> &str {
+        &self.detail
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        tracing::warn!(
+            code = self.code,
+            status = self.status.as_u16(),
+            detail = %self.detail,
+            "request failed"
+        );
+
+        // A few routes signal "nothing to return" through the error path, and
+        // 204 responses must not carry a body.
+        if self.status == StatusCode::NO_CONTENT {
+            return self.status.into_response();
+        }
+
+        let problem = ProblemDetails {
+            problem_type: "about:blank",
+            title: self.status.canonical_reason().unwrap_or_default(),
+            status: self.status.as_u16(),
+            detail: self.detail,
+            code: self.code,
+            request_id: request_id::current(),
+        };
+
+        (
+            self.status,
+            [(CONTENT_TYPE, "application/problem+json")],
+            serde_json::to_string(&problem).unwrap(),
+        )
+            .into_response()
+    }
+}
+
+impl From<QueryRejection> for AppError {
+    fn from(rejection: QueryRejection) -> Self {
+        AppError::new(
+            rejection.status(),
+            "request.invalid_query",
+            rejection.body_text(),
+        )
+    }
+}
+
+impl From<PathRejection> for AppError {
+    fn from(rejection: PathRejection) -> Self {
+        AppError::new(
+            rejection.status(),
+            "request.invalid_path",
+            rejection.body_text(),
+        )
+    }
+}
+
+impl From<JsonRejection> for AppError {
+    fn from(rejection: JsonRejection) -> Self {
+        AppError::new(
+            rejection.status(),
+            "request.invalid_json",
+            rejection.body_text(),
+        )
+    }
+}
+
+impl From<MultipartRejection> for AppError {
+    fn from(rejection: MultipartRejection) -> Self {
+        AppError::new(
+            rejection.status(),
+            "request.invalid_multipart",
+            rejection.body_text(),
+        )
+    }
+}
@@ -0,0 +1,54 @@
+//! `Query`, `Path`, `Json` and `Multipart` extractors that reject with the
+//! shared problem+json body instead of axum's plain-text one.
+
+use std::ops::Deref;
+
+use axum::{
+    extract::{FromRequest, FromRequestParts},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+
+use crate::error::AppError;
+
+#[cfg(any(feature = "dec2", feature = "dec19"))]
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Query), rejection(AppError))]
+pub struct Query<T>(pub T);
+
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Path), rejection(AppError))]
+pub struct Path<T>(pub T);
+
+/// Also usable as a response, like `axum::Json`.
+#[derive(FromRequest)]
+#[from_request(via(axum::Json), rejection(AppError))]
+pub struct Json<T>(pub T);
+
+#[cfg(any(feature = "dec5", feature = "dec23"))]
+#[derive(FromRequest)]
+#[from_request(via(axum::extract::Multipart), rejection(AppError))]
+pub struct Multipart(pub axum::extract::Multipart);
+
+#[cfg(any(feature = "dec2", feature = "dec19"))]
+impl<T> Deref for Query<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> Deref for Json<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Response {
+        axum::Json(self.0).into_response()
+    }
+}
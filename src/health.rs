@@ -0,0 +1,127 @@
+use std::{collections::HashSet, sync::Arc};
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde::Serialize;
+
+use crate::{AppState, MIGRATOR};
+
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+enum CheckStatus {
+    Ok,
+    Failing,
+}
+
+#[derive(Serialize)]
+struct DependencyCheck {
+    status: CheckStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct MigrationCheck {
+    status: CheckStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    pending: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct ReadinessChecks {
+    database: DependencyCheck,
+    migrations: MigrationCheck,
+}
+
+#[derive(Serialize)]
+struct Readiness {
+    status: CheckStatus,
+    checks: ReadinessChecks,
+}
+
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    tag = "operations",
+    responses((status = 200, description = "Process is up", body = Object)),
+)]
+pub async fn healthz() -> impl IntoResponse {
+    Json(serde_json::json!({ "status": "ok" }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "operations",
+    responses(
+        (status = 200, description = "Postgres reachable and all migrations applied", body = Object),
+        (status = 503, description = "A dependency check failed", body = Object),
+    ),
+)]
+pub async fn readyz(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let database = match sqlx::query("SELECT 1").execute(&state.pool).await {
+        Ok(_) => DependencyCheck {
+            status: CheckStatus::Ok,
+            error: None,
+        },
+        Err(error) => DependencyCheck {
+            status: CheckStatus::Failing,
+            error: Some(error.to_string()),
+        },
+    };
+
+    let migrations = check_migrations(&state.pool).await;
+
+    let ready =
+        matches!(database.status, CheckStatus::Ok) && matches!(migrations.status, CheckStatus::Ok);
+    let (status_code, status) = if ready {
+        (StatusCode::OK, CheckStatus::Ok)
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, CheckStatus::Failing)
+    };
+
+    (
+        status_code,
+        Json(Readiness {
+            status,
+            checks: ReadinessChecks {
+                database,
+                migrations,
+            },
+        }),
+    )
+}
+
+async fn check_migrations(pool: &sqlx::PgPool) -> MigrationCheck {
+    let applied: HashSet<i64> =
+        match sqlx::query_scalar::<_, i64>("SELECT version FROM _sqlx_migrations WHERE success")
+            .fetch_all(pool)
+            .await
+        {
+            Ok(versions) => versions.into_iter().collect(),
+            Err(error) => {
+                return MigrationCheck {
+                    status: CheckStatus::Failing,
+                    error: Some(error.to_string()),
+                    pending: Vec::new(),
+                }
+            }
+        };
+
+    let pending = MIGRATOR
+        .iter()
+        .filter(|migration| !migration.migration_type.is_down_migration())
+        .filter(|migration| !applied.contains(&migration.version))
+        .map(|migration| format!("{}_{}", migration.version, migration.description))
+        .collect::<Vec<_>>();
+
+    MigrationCheck {
+        status: if pending.is_empty() {
+            CheckStatus::Ok
+        } else {
+            CheckStatus::Failing
+        },
+        error: None,
+        pending,
+    }
+}
@@ -0,0 +1,355 @@
+#[cfg(feature = "dec19")]
+use std::collections::HashMap;
+use std::sync::Arc;
+#[cfg(feature = "dec12")]
+use std::sync::RwLock;
+
+#[cfg(feature = "dec19")]
+use axum::routing::delete;
+#[cfg(any(
+    feature = "dec2",
+    feature = "dec5",
+    feature = "dec12",
+    feature = "dec16",
+    feature = "dec19",
+    feature = "dec23"
+))]
+use axum::routing::post;
+use axum::{
+    middleware,
+    routing::{get, put},
+    Router,
+};
+#[cfg(feature = "dec12")]
+use challenge_dec12::MilkCookieGame;
+#[cfg(feature = "dec2")]
+use challenge_dec2::Anonymizer;
+use challenge_intro::{hello_bird, seek_and_find};
+#[cfg(feature = "dec5")]
+use config::ManifestRules;
+use config::ServerConfig;
+#[cfg(feature = "dec5")]
+use dependency_tree::RegistryIndex;
+use health::{healthz, readyz};
+#[cfg(feature = "dec9")]
+use leaky_bucket_lite::LeakyBucket;
+use modules::{list_modules, update_module, ModuleToggles};
+#[cfg(any(
+    feature = "dec2",
+    feature = "dec5",
+    feature = "dec9",
+    feature = "dec12",
+    feature = "dec16",
+    feature = "dec19",
+    feature = "dec23"
+))]
+use modules::{toggleable, ChallengeModule};
+use monitoring::prometheus_metrics;
+use openapi::swagger_ui;
+use snapshot::AppSnapshot;
+use sqlx::migrate::Migrator;
+use tower_http::{services::ServeDir, trace::TraceLayer};
+
+#[cfg(feature = "dec12")]
+mod challenge_dec12;
+#[cfg(feature = "dec16")]
+mod challenge_dec16;
+#[cfg(feature = "dec19")]
+mod challenge_dec19;
+#[cfg(feature = "dec2")]
+mod challenge_dec2;
+#[cfg(feature = "dec23")]
+mod challenge_dec23;
+#[cfg(feature = "dec5")]
+mod challenge_dec5;
+#[cfg(feature = "dec9")]
+mod challenge_dec9;
+mod challenge_intro;
+pub mod config;
+#[cfg(feature = "dec5")]
+mod dependency_tree;
+pub mod error;
+mod extract;
+mod health;
+pub mod modules;
+pub mod monitoring;
+#[cfg(any(feature = "dec2", feature = "dec5"))]
+mod negotiation;
+mod openapi;
+#[cfg(feature = "replay")]
+pub mod replay;
+pub mod request_id;
+mod snapshot;
+pub mod telemetry;
+
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
+pub struct AppState {
+    #[cfg(feature = "dec9")]
+    leaky_milk_bucket: LeakyBucket,
+    #[cfg(feature = "dec12")]
+    milk_cookie_game: RwLock<MilkCookieGame>,
+    pool: sqlx::PgPool,
+    #[cfg(feature = "dec19")]
+    quote_pagination: tokio::sync::RwLock<HashMap<String, i32>>,
+    modules: ModuleToggles,
+    admin_token: Option<String>,
+    #[cfg(feature = "dec2")]
+    anonymizer: Option<Anonymizer>,
+    #[cfg(feature = "dec5")]
+    manifest_rules: ManifestRules,
+    #[cfg(feature = "dec5")]
+    registry_index: Option<RegistryIndex>,
+}
+
+impl AppState {
+    pub fn new(pool: sqlx::PgPool, config: &ServerConfig) -> Self {
+        AppState::from_snapshot(pool, config, AppSnapshot::default())
+    }
+
+    /// Builds the state for the compiled-in modules, taking whatever the
+    /// snapshot holds and falling back to the config for the rest.
+    #[cfg_attr(
+        not(any(feature = "dec9", feature = "dec12", feature = "dec19")),
+        allow(unused_variables)
+    )]
+    fn from_snapshot(pool: sqlx::PgPool, config: &ServerConfig, snapshot: AppSnapshot) -> Self {
+        AppState {
+            #[cfg(feature = "dec9")]
+            leaky_milk_bucket: LeakyBucket::builder()
+                .max(config.milk_bucket.max)
+                .tokens(
+                    snapshot
+                        .milk_bucket_tokens
+                        .unwrap_or(config.milk_bucket.tokens)
+                        .min(config.milk_bucket.max),
+                )
+                .refill_interval(config.milk_bucket.refill_interval())
+                .refill_amount(config.milk_bucket.refill_amount)
+                .build(),
+            #[cfg(feature = "dec12")]
+            milk_cookie_game: RwLock::new(
+                snapshot
+                    .milk_cookie_game
+                    .filter(|snapshot| {
+                        let fits = snapshot.size() == config.board_size;
+                        if !fits {
+                            tracing::warn!(
+                                snapshot_size = snapshot.size(),
+                                board_size = config.board_size,
+                                "board size changed since the snapshot, starting a new game"
+                            );
+                        }
+                        fits
+                    })
+                    .map(MilkCookieGame::restore)
+                    .unwrap_or_else(|| MilkCookieGame::new(config.board_size)),
+            ),
+            pool,
+            #[cfg(feature = "dec19")]
+            quote_pagination: tokio::sync::RwLock::new(snapshot.quote_pagination),
+            modules: ModuleToggles::new(&config.disabled_modules),
+            admin_token: config.admin_token.clone(),
+            #[cfg(feature = "dec2")]
+            anonymizer: config.anonymization_secret.as_deref().map(Anonymizer::new),
+            #[cfg(feature = "dec5")]
+            manifest_rules: config.manifest_rules.clone(),
+            #[cfg(feature = "dec5")]
+            registry_index: config.registry_index.as_deref().map(RegistryIndex::new),
+        }
+    }
+}
+
+pub fn router(app_state: Arc<AppState>) -> Router {
+    monitoring::install_recorder();
+
+    #[cfg_attr(
+        not(any(
+            feature = "dec2",
+            feature = "dec5",
+            feature = "dec9",
+            feature = "dec12",
+            feature = "dec16",
+            feature = "dec19",
+            feature = "dec23"
+        )),
+        allow(unused_mut)
+    )]
+    let mut router = Router::new()
+        .route("/", get(hello_bird))
+        .route("/-1/seek", get(seek_and_find));
+
+    #[cfg(feature = "dec2")]
+    {
+        router = router.merge(toggleable(
+            &app_state,
+            ChallengeModule::Dec2,
+            Router::new()
+                .route("/2/dest", get(challenge_dec2::egregious_encryption_dest))
+                .route("/2/key", get(challenge_dec2::egregious_encryption_key))
+                .route(
+                    "/2/v6/dest",
+                    get(challenge_dec2::egregious_encryption_dest_v6),
+                )
+                .route(
+                    "/2/v6/key",
+                    get(challenge_dec2::egregious_encryption_key_v6),
+                )
+                .route(
+                    "/2/any/dest",
+                    get(challenge_dec2::egregious_encryption_dest_any),
+                )
+                .route(
+                    "/2/any/key",
+                    get(challenge_dec2::egregious_encryption_key_any),
+                )
+                .route(
+                    "/2/mac/dest",
+                    get(challenge_dec2::egregious_encryption_dest_mac),
+                )
+                .route(
+                    "/2/mac/key",
+                    get(challenge_dec2::egregious_encryption_key_mac),
+                )
+                .route(
+                    "/2/mac/link-local",
+                    get(challenge_dec2::egregious_encryption_link_local),
+                )
+                .route(
+                    "/2/eui64/dest",
+                    get(challenge_dec2::egregious_encryption_dest_eui64),
+                )
+                .route(
+                    "/2/eui64/key",
+                    get(challenge_dec2::egregious_encryption_key_eui64),
+                )
+                .route(
+                    "/2/cidr/dest",
+                    get(challenge_dec2::egregious_encryption_cidr_dest),
+                )
+                .route(
+                    "/2/cidr/key",
+                    get(challenge_dec2::egregious_encryption_cidr_key),
+                )
+                .route("/2/anonymize", get(challenge_dec2::anonymize_address))
+                .route("/2/deanonymize", get(challenge_dec2::deanonymize_address))
+                .route("/2/batch", post(challenge_dec2::egregious_encryption_batch))
+                .route(
+                    "/2/stream",
+                    post(challenge_dec2::egregious_encryption_stream),
+                ),
+        ));
+    }
+
+    #[cfg(feature = "dec5")]
+    {
+        router = router.merge(toggleable(
+            &app_state,
+            ChallengeModule::Dec5,
+            Router::new()
+                .route("/5/manifest", post(challenge_dec5::car_go_festivity))
+                .route("/5/lint", post(challenge_dec5::lint_cargo_manifest))
+                .route("/5/convert", post(challenge_dec5::convert_cargo_manifest))
+                .route("/5/manifest/tree", post(challenge_dec5::dependency_tree)),
+        ));
+    }
+
+    #[cfg(feature = "dec9")]
+    {
+        router = router.merge(toggleable(
+            &app_state,
+            ChallengeModule::Dec9,
+            Router::new()
+                .route("/9/milk", post(challenge_dec9::milk_bucket_leaky))
+                .route("/9/refill", post(challenge_dec9::milk_bucket_refill)),
+        ));
+    }
+
+    #[cfg(feature = "dec12")]
+    {
+        router = router.merge(toggleable(
+            &app_state,
+            ChallengeModule::Dec12,
+            Router::new()
+                .route("/12/board", get(challenge_dec12::milk_cookie_game_state))
+                .route("/12/reset", post(challenge_dec12::milk_cookie_game_reset))
+                .route(
+                    "/12/place/:team/:column",
+                    post(challenge_dec12::milk_cookie_game_place),
+                )
+                .route(
+                    "/12/random-board",
+                    get(challenge_dec12::milk_cookie_not_random),
+                ),
+        ));
+    }
+
+    #[cfg(feature = "dec16")]
+    {
+        router = router.merge(toggleable(
+            &app_state,
+            ChallengeModule::Dec16,
+            Router::new()
+                .route("/16/wrap", post(challenge_dec16::wrap_present))
+                .route("/16/unwrap", get(challenge_dec16::unwrap_present))
+                .route(
+                    "/16/decode",
+                    post(challenge_dec16::unwrap_encrypted_present),
+                ),
+        ));
+    }
+
+    #[cfg(feature = "dec19")]
+    {
+        router = router.merge(toggleable(
+            &app_state,
+            ChallengeModule::Dec19,
+            Router::new()
+                .route("/19/reset", post(challenge_dec19::quotes_reset))
+                .route("/19/cite/:id", get(challenge_dec19::quote_by_id))
+                .route(
+                    "/19/remove/:id",
+                    delete(challenge_dec19::remove_quote_by_id),
+                )
+                .route("/19/undo/:id", put(challenge_dec19::undo_quote_by_id))
+                .route("/19/draft", post(challenge_dec19::draft_quote))
+                .route("/19/list", get(challenge_dec19::paginated_quotes)),
+        ));
+    }
+
+    #[cfg(feature = "dec23")]
+    {
+        router = router.merge(toggleable(
+            &app_state,
+            ChallengeModule::Dec23,
+            Router::new()
+                .route("/23/star", get(challenge_dec23::htmx_star))
+                .route(
+                    "/23/present/:color",
+                    get(challenge_dec23::htmx_present_color),
+                )
+                .route(
+                    "/23/ornament/:state/:n",
+                    get(challenge_dec23::htmx_css_animations),
+                )
+                .route("/23/lockfile", post(challenge_dec23::htmx_form)),
+        ));
+    }
+
+    router
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .route("/metrics", get(prometheus_metrics))
+        .merge(swagger_ui())
+        .route("/admin/modules", get(list_modules))
+        .route("/admin/modules/:module", put(update_module))
+        .nest_service("/assets", ServeDir::new("assets"))
+        .layer(middleware::from_fn(monitoring::track_requests))
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(telemetry::make_request_span)
+                .on_response(telemetry::on_response),
+        )
+        .layer(middleware::from_fn(request_id::assign_request_id))
+        .with_state(app_state)
+}
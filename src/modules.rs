@@ -0,0 +1,246 @@
+use std::{
+    collections::HashSet,
+    fmt::Display,
+    str::FromStr,
+    sync::{Arc, RwLock},
+};
+
+use axum::{
+    extract::{Request, State},
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
+use utoipa::ToSchema;
+
+use crate::{
+    error::{AppError, ProblemDetails},
+    extract::{Json, Path},
+    AppState,
+};
+
+/// Challenge modules that can be compiled out with cargo features and
+/// switched off at runtime.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ChallengeModule {
+    Dec2,
+    Dec5,
+    Dec9,
+    Dec12,
+    Dec16,
+    Dec19,
+    Dec23,
+}
+
+impl ChallengeModule {
+    pub const ALL: [ChallengeModule; 7] = [
+        ChallengeModule::Dec2,
+        ChallengeModule::Dec5,
+        ChallengeModule::Dec9,
+        ChallengeModule::Dec12,
+        ChallengeModule::Dec16,
+        ChallengeModule::Dec19,
+        ChallengeModule::Dec23,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ChallengeModule::Dec2 => "dec2",
+            ChallengeModule::Dec5 => "dec5",
+            ChallengeModule::Dec9 => "dec9",
+            ChallengeModule::Dec12 => "dec12",
+            ChallengeModule::Dec16 => "dec16",
+            ChallengeModule::Dec19 => "dec19",
+            ChallengeModule::Dec23 => "dec23",
+        }
+    }
+
+    /// Whether the module's cargo feature was enabled for this build.
+    pub fn compiled(self) -> bool {
+        match self {
+            ChallengeModule::Dec2 => cfg!(feature = "dec2"),
+            ChallengeModule::Dec5 => cfg!(feature = "dec5"),
+            ChallengeModule::Dec9 => cfg!(feature = "dec9"),
+            ChallengeModule::Dec12 => cfg!(feature = "dec12"),
+            ChallengeModule::Dec16 => cfg!(feature = "dec16"),
+            ChallengeModule::Dec19 => cfg!(feature = "dec19"),
+            ChallengeModule::Dec23 => cfg!(feature = "dec23"),
+        }
+    }
+}
+
+impl Display for ChallengeModule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for ChallengeModule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ChallengeModule::ALL
+            .into_iter()
+            .find(|module| module.name() == s)
+            .ok_or_else(|| format!("unknown challenge module {s:?}"))
+    }
+}
+
+pub struct ModuleToggles {
+    disabled: RwLock<HashSet<ChallengeModule>>,
+}
+
+impl ModuleToggles {
+    pub fn new(disabled: &[ChallengeModule]) -> Self {
+        ModuleToggles {
+            disabled: RwLock::new(disabled.iter().copied().collect()),
+        }
+    }
+
+    pub fn is_enabled(&self, module: ChallengeModule) -> bool {
+        module.compiled() && !self.disabled.read().unwrap().contains(&module)
+    }
+
+    fn set_enabled(&self, module: ChallengeModule, enabled: bool) {
+        let mut disabled = self.disabled.write().unwrap();
+        if enabled {
+            disabled.remove(&module);
+        } else {
+            disabled.insert(module);
+        }
+    }
+}
+
+/// Wraps a module's routes so they answer 503 while it is switched off.
+pub(crate) fn toggleable(
+    app_state: &Arc<AppState>,
+    module: ChallengeModule,
+    routes: Router<Arc<AppState>>,
+) -> Router<Arc<AppState>> {
+    routes.route_layer(middleware::from_fn_with_state(
+        (app_state.clone(), module),
+        require_enabled,
+    ))
+}
+
+async fn require_enabled(
+    State((state, module)): State<(Arc<AppState>, ChallengeModule)>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !state.modules.is_enabled(module) {
+        return AppError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "module.disabled",
+            format!("The {module} challenge is currently disabled"),
+        )
+        .into_response();
+    }
+
+    next.run(request).await
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ModuleStatus {
+    module: ChallengeModule,
+    compiled: bool,
+    enabled: bool,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ModuleUpdate {
+    enabled: bool,
+}
+
+fn module_status(state: &AppState, module: ChallengeModule) -> ModuleStatus {
+    ModuleStatus {
+        module,
+        compiled: module.compiled(),
+        enabled: state.modules.is_enabled(module),
+    }
+}
+
+fn authorize_admin(state: &AppState, headers: &HeaderMap) -> Result<(), AppError> {
+    let Some(admin_token) = &state.admin_token else {
+        return Err(AppError::new(
+            StatusCode::FORBIDDEN,
+            "admin.disabled",
+            "Admin endpoints are disabled until an admin_token is configured",
+        ));
+    };
+
+    let bearer = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    // Constant time, so response timing does not reveal how much of the
+    // token a guess got right.
+    let authorized =
+        bearer.is_some_and(|bearer| bool::from(bearer.as_bytes().ct_eq(admin_token.as_bytes())));
+    if !authorized {
+        return Err(AppError::new(
+            StatusCode::UNAUTHORIZED,
+            "admin.unauthorized",
+            "Missing or wrong admin bearer token",
+        ));
+    }
+
+    Ok(())
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/modules",
+    tag = "admin",
+    responses((status = 200, description = "Every challenge module and whether it is served", body = Vec<ModuleStatus>), (status = 401, description = "Wrong admin token", body = ProblemDetails, content_type = "application/problem+json"), (status = 403, description = "No admin token configured", body = ProblemDetails, content_type = "application/problem+json")),
+)]
+pub async fn list_modules(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, AppError> {
+    authorize_admin(&state, &headers)?;
+
+    Ok(Json(
+        ChallengeModule::ALL
+            .into_iter()
+            .map(|module| module_status(&state, module))
+            .collect::<Vec<_>>(),
+    ))
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/modules/{module}",
+    tag = "admin",
+    params(("module" = ChallengeModule, Path, description = "Module name, e.g. dec9")),
+    request_body = ModuleUpdate,
+    responses((status = 200, description = "The module's new status", body = ModuleStatus), (status = 401, description = "Wrong admin token", body = ProblemDetails, content_type = "application/problem+json"), (status = 403, description = "No admin token configured", body = ProblemDetails, content_type = "application/problem+json"), (status = 404, description = "Unknown module", body = ProblemDetails, content_type = "application/problem+json"), (status = 409, description = "Module left out of this build", body = ProblemDetails, content_type = "application/problem+json")),
+)]
+pub async fn update_module(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(module): Path<String>,
+    Json(update): Json<ModuleUpdate>,
+) -> Result<impl IntoResponse, AppError> {
+    authorize_admin(&state, &headers)?;
+
+    let module = module
+        .parse::<ChallengeModule>()
+        .map_err(|detail| AppError::new(StatusCode::NOT_FOUND, "module.unknown", detail))?;
+    if !module.compiled() {
+        return Err(AppError::new(
+            StatusCode::CONFLICT,
+            "module.not_compiled",
+            format!("The {module} challenge was left out of this build"),
+        ));
+    }
+
+    state.modules.set_enabled(module, update.enabled);
+    tracing::info!(%module, enabled = update.enabled, "challenge module toggled");
+
+    Ok(Json(module_status(&state, module)))
+}
@@ -0,0 +1,95 @@
+use std::{
+    sync::{Arc, OnceLock},
+    time::Instant,
+};
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::header::CONTENT_TYPE,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use metrics::{counter, gauge, histogram};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+
+use crate::AppState;
+
+const REQUEST_DURATION_METRIC: &str = "http_request_duration_seconds";
+const REQUEST_DURATION_BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+];
+
+static PROMETHEUS_HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+
+/// Installs the process-wide Prometheus recorder on first use. Later calls,
+/// e.g. from routers built in tests, share the same recorder.
+pub fn install_recorder() -> &'static PrometheusHandle {
+    PROMETHEUS_HANDLE.get_or_init(|| {
+        PrometheusBuilder::new()
+            .set_buckets_for_metric(
+                Matcher::Full(String::from(REQUEST_DURATION_METRIC)),
+                REQUEST_DURATION_BUCKETS,
+            )
+            .unwrap()
+            .install_recorder()
+            .expect("no other metrics recorder is installed")
+    })
+}
+
+pub async fn track_requests(request: Request, next: Next) -> Response {
+    let started = Instant::now();
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| String::from("<unmatched>"));
+
+    let response = next.run(request).await;
+
+    let status = response.status().as_u16().to_string();
+    counter!(
+        "http_requests_total",
+        "method" => method.clone(),
+        "route" => route.clone(),
+        "status" => status,
+    )
+    .increment(1);
+    histogram!(REQUEST_DURATION_METRIC, "method" => method, "route" => route)
+        .record(started.elapsed().as_secs_f64());
+
+    response
+}
+
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "operations",
+    responses((status = 200, description = "Prometheus text exposition", body = String, content_type = "text/plain")),
+)]
+#[cfg_attr(not(any(feature = "dec9", feature = "dec19")), allow(unused_variables))]
+pub async fn prometheus_metrics(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    #[cfg(feature = "dec9")]
+    gauge!("milk_bucket_tokens").set(state.leaky_milk_bucket.tokens().await as f64);
+
+    #[cfg(feature = "dec19")]
+    {
+        gauge!("quote_pagination_tokens").set(state.quote_pagination.read().await.len() as f64);
+
+        match sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM quotes")
+            .fetch_one(&state.pool)
+            .await
+        {
+            Ok(quote_rows) => gauge!("quote_rows").set(quote_rows as f64),
+            Err(error) => tracing::warn!(%error, "could not count quotes for metrics"),
+        }
+    }
+
+    let handle = install_recorder();
+    handle.run_upkeep();
+
+    (
+        [(CONTENT_TYPE, "text/plain; version=0.0.4")],
+        handle.render(),
+    )
+}
@@ -0,0 +1,77 @@
+use axum::http::{header::ACCEPT, HeaderMap};
+
+/// The `type/subtype` of a `Content-Type` or `Accept` entry, without
+/// parameters like `charset`.
+pub fn essence(media_type: &str) -> &str {
+    media_type.split(';').next().unwrap_or_default().trim()
+}
+
+/// The `q` parameter of an `Accept` entry, 1 when it is missing or invalid.
+fn quality(entry: &str) -> f32 {
+    entry
+        .split(';')
+        .skip(1)
+        .find_map(|param| {
+            let (name, value) = param.split_once('=')?;
+            name.trim().eq_ignore_ascii_case("q").then_some(value)
+        })
+        .and_then(|value| value.trim().parse::<f32>().ok())
+        .filter(|quality| (0.0..=1.0).contains(quality))
+        .unwrap_or(1.0)
+}
+
+/// Media ranges listed in the `Accept` header with their quality, highest
+/// first and in the order given among equals.
+fn accepted_media_types(headers: &HeaderMap) -> Option<Vec<(&str, f32)>> {
+    let accept = headers.get(ACCEPT)?.to_str().ok()?;
+
+    let mut media_types: Vec<_> = accept
+        .split(',')
+        .map(|entry| (essence(entry), quality(entry)))
+        .filter(|(media_type, _)| !media_type.is_empty())
+        .collect();
+    media_types.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+    Some(media_types)
+}
+
+/// Whether `range`, possibly `*/*` or `type/*`, covers `media_type`.
+fn covers(range: &str, media_type: &str) -> bool {
+    match range.strip_suffix("/*") {
+        Some("*") => true,
+        Some(range_type) => media_type
+            .split_once('/')
+            .is_some_and(|(media_type, _)| media_type.eq_ignore_ascii_case(range_type)),
+        None => range.eq_ignore_ascii_case(media_type),
+    }
+}
+
+/// Whether the client prefers `application/json` over `text/plain`. Routes
+/// that answer in plain text by default switch to JSON when it does.
+pub fn accepts_json(headers: &HeaderMap) -> bool {
+    preferred_media_type(headers, &["text/plain", "application/json"]) == Some("application/json")
+}
+
+/// The one of `offered` the client ranks highest, or the first offered one
+/// when it sends no `Accept` header. A type listed with `q=0` is never
+/// picked, even when a wildcard covers it.
+pub fn preferred_media_type<'a>(headers: &HeaderMap, offered: &[&'a str]) -> Option<&'a str> {
+    let Some(media_types) = accepted_media_types(headers) else {
+        return offered.first().copied();
+    };
+
+    let excluded = |offered: &str| {
+        media_types
+            .iter()
+            .any(|(range, quality)| *quality == 0.0 && range.eq_ignore_ascii_case(offered))
+    };
+
+    media_types
+        .iter()
+        .filter(|(_, quality)| *quality > 0.0)
+        .find_map(|(range, _)| {
+            offered
+                .iter()
+                .copied()
+                .find(|offered| covers(range, offered) && !excluded(offered))
+        })
+}
@@ -0,0 +1,193 @@
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+
+#[cfg(feature = "dec12")]
+use crate::challenge_dec12;
+#[cfg(feature = "dec16")]
+use crate::challenge_dec16;
+#[cfg(feature = "dec19")]
+use crate::challenge_dec19;
+#[cfg(feature = "dec2")]
+use crate::challenge_dec2;
+#[cfg(feature = "dec23")]
+use crate::challenge_dec23;
+#[cfg(feature = "dec9")]
+use crate::challenge_dec9;
+#[cfg(feature = "dec5")]
+use crate::{challenge_dec5, dependency_tree};
+use crate::{challenge_intro, error::ProblemDetails, health, modules, monitoring};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "shuttlings-cch24",
+        description = "Shuttle's Christmas Code Hunt 2024 challenges"
+    ),
+    paths(
+        challenge_intro::hello_bird,
+        challenge_intro::seek_and_find,
+        health::healthz,
+        health::readyz,
+        monitoring::prometheus_metrics,
+        modules::list_modules,
+        modules::update_module,
+    ),
+    components(schemas(
+        ProblemDetails,
+        modules::ChallengeModule,
+        modules::ModuleStatus,
+        modules::ModuleUpdate
+    ))
+)]
+struct ApiDoc;
+
+#[cfg(feature = "dec2")]
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        challenge_dec2::egregious_encryption_dest,
+        challenge_dec2::egregious_encryption_key,
+        challenge_dec2::egregious_encryption_dest_v6,
+        challenge_dec2::egregious_encryption_key_v6,
+        challenge_dec2::egregious_encryption_dest_any,
+        challenge_dec2::egregious_encryption_key_any,
+        challenge_dec2::egregious_encryption_dest_mac,
+        challenge_dec2::egregious_encryption_key_mac,
+        challenge_dec2::egregious_encryption_link_local,
+        challenge_dec2::egregious_encryption_dest_eui64,
+        challenge_dec2::egregious_encryption_key_eui64,
+        challenge_dec2::egregious_encryption_cidr_dest,
+        challenge_dec2::egregious_encryption_cidr_key,
+        challenge_dec2::anonymize_address,
+        challenge_dec2::deanonymize_address,
+        challenge_dec2::egregious_encryption_batch,
+        challenge_dec2::egregious_encryption_stream,
+    ),
+    components(schemas(
+        challenge_dec2::CipherKind,
+        challenge_dec2::AddressRepresentation,
+        challenge_dec2::CidrDestination,
+        challenge_dec2::CidrKey,
+        challenge_dec2::BatchOperation,
+        challenge_dec2::BatchItemResult,
+        challenge_dec2::BatchItemError,
+    ))
+)]
+struct Dec2Doc;
+
+#[cfg(feature = "dec5")]
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        challenge_dec5::car_go_festivity,
+        challenge_dec5::lint_cargo_manifest,
+        challenge_dec5::convert_cargo_manifest,
+        challenge_dec5::dependency_tree,
+    ),
+    components(schemas(
+        challenge_dec5::Order,
+        challenge_dec5::OrderRejection,
+        challenge_dec5::SkippedOrder,
+        challenge_dec5::OrderSummary,
+        challenge_dec5::LintSeverity,
+        challenge_dec5::LintFinding,
+        dependency_tree::DependencyTree,
+        dependency_tree::DependencyNode,
+        dependency_tree::UnresolvedDependency,
+    ))
+)]
+struct Dec5Doc;
+
+#[cfg(feature = "dec9")]
+#[derive(OpenApi)]
+#[openapi(
+    paths(challenge_dec9::milk_bucket_leaky, challenge_dec9::milk_bucket_refill),
+    components(schemas(challenge_dec9::ConversionPayload))
+)]
+struct Dec9Doc;
+
+#[cfg(feature = "dec12")]
+#[derive(OpenApi)]
+#[openapi(paths(
+    challenge_dec12::milk_cookie_game_state,
+    challenge_dec12::milk_cookie_game_reset,
+    challenge_dec12::milk_cookie_game_place,
+    challenge_dec12::milk_cookie_not_random,
+))]
+struct Dec12Doc;
+
+#[cfg(feature = "dec16")]
+#[derive(OpenApi)]
+#[openapi(paths(
+    challenge_dec16::wrap_present,
+    challenge_dec16::unwrap_present,
+    challenge_dec16::unwrap_encrypted_present,
+))]
+struct Dec16Doc;
+
+#[cfg(feature = "dec19")]
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        challenge_dec19::quotes_reset,
+        challenge_dec19::quote_by_id,
+        challenge_dec19::remove_quote_by_id,
+        challenge_dec19::undo_quote_by_id,
+        challenge_dec19::draft_quote,
+        challenge_dec19::paginated_quotes,
+    ),
+    components(schemas(
+        challenge_dec19::Quote,
+        challenge_dec19::QuoteInsert,
+        challenge_dec19::PaginatedQuotes,
+    ))
+)]
+struct Dec19Doc;
+
+#[cfg(feature = "dec23")]
+#[derive(OpenApi)]
+#[openapi(paths(
+    challenge_dec23::htmx_star,
+    challenge_dec23::htmx_present_color,
+    challenge_dec23::htmx_css_animations,
+    challenge_dec23::htmx_form,
+))]
+struct Dec23Doc;
+
+/// The document for the modules compiled into this build.
+fn api_doc() -> utoipa::openapi::OpenApi {
+    #[cfg_attr(
+        not(any(
+            feature = "dec2",
+            feature = "dec5",
+            feature = "dec9",
+            feature = "dec12",
+            feature = "dec16",
+            feature = "dec19",
+            feature = "dec23"
+        )),
+        allow(unused_mut)
+    )]
+    let mut doc = ApiDoc::openapi();
+    #[cfg(feature = "dec2")]
+    doc.merge(Dec2Doc::openapi());
+    #[cfg(feature = "dec5")]
+    doc.merge(Dec5Doc::openapi());
+    #[cfg(feature = "dec9")]
+    doc.merge(Dec9Doc::openapi());
+    #[cfg(feature = "dec12")]
+    doc.merge(Dec12Doc::openapi());
+    #[cfg(feature = "dec16")]
+    doc.merge(Dec16Doc::openapi());
+    #[cfg(feature = "dec19")]
+    doc.merge(Dec19Doc::openapi());
+    #[cfg(feature = "dec23")]
+    doc.merge(Dec23Doc::openapi());
+    doc
+}
+
+/// Swagger UI at `/docs`, with its assets embedded in the binary, reading
+/// the document served at `/openapi.json`.
+pub fn swagger_ui() -> SwaggerUi {
+    SwaggerUi::new("/docs").url("/openapi.json", api_doc())
+}
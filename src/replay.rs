@@ -0,0 +1,225 @@
+//! Recorded request/expected-response pairs, stored one JSON object per line:
+//!
+//! ```json
+//! {"name": "v4 dest", "request": {"method": "GET", "path": "/2/dest?from=10.0.0.0&key=1.2.255.255"}, "expect": {"status": 200, "body": "11.2.255.255"}}
+//! ```
+//!
+//! `request.headers` and `request.body` are optional. `expect.headers` only
+//! checks the listed headers, `expect.body` compares the body text exactly and
+//! `expect.json` compares the body as JSON, where objects only need to contain
+//! the listed keys.
+
+use std::{collections::BTreeMap, fmt::Display, fs, path::Path};
+
+use anyhow::Context;
+use axum::{
+    body::{Body, Bytes, HttpBody},
+    http::{HeaderMap, Method, Request, Response, StatusCode},
+};
+use http_body_util::BodyExt;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use similar::TextDiff;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Fixture {
+    pub name: String,
+    pub request: FixtureRequest,
+    pub expect: FixtureExpectation,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FixtureRequest {
+    pub method: String,
+    pub path: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub body: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FixtureExpectation {
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub json: Option<Value>,
+}
+
+pub struct RecordedResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+#[derive(Debug)]
+pub enum Mismatch {
+    Status {
+        expected: u16,
+        actual: u16,
+    },
+    Header {
+        name: String,
+        expected: String,
+        actual: Option<String>,
+    },
+    Body {
+        diff: String,
+    },
+    Json {
+        expected: Value,
+        actual: Value,
+    },
+}
+
+impl Fixture {
+    /// Builds the request against `base`, e.g. `http://localhost:8000`, or
+    /// with a bare path when `base` is empty.
+    pub fn to_request(&self, base: &str) -> anyhow::Result<Request<Body>> {
+        let method = Method::from_bytes(self.request.method.as_bytes())
+            .with_context(|| format!("{}: invalid method", self.name))?;
+        let mut request = Request::builder().method(method).uri(format!(
+            "{}{}",
+            base.trim_end_matches('/'),
+            self.request.path
+        ));
+        for (name, value) in &self.request.headers {
+            request = request.header(name, value);
+        }
+
+        request
+            .body(Body::from(self.request.body.clone()))
+            .with_context(|| format!("{}: invalid request", self.name))
+    }
+
+    pub fn check(&self, response: &RecordedResponse) -> Vec<Mismatch> {
+        let mut mismatches = Vec::new();
+        let expect = &self.expect;
+
+        if response.status.as_u16() != expect.status {
+            mismatches.push(Mismatch::Status {
+                expected: expect.status,
+                actual: response.status.as_u16(),
+            });
+        }
+
+        for (name, expected) in &expect.headers {
+            let actual = response
+                .headers
+                .get(name)
+                .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned());
+            if actual.as_deref() != Some(expected.as_str()) {
+                mismatches.push(Mismatch::Header {
+                    name: name.clone(),
+                    expected: expected.clone(),
+                    actual,
+                });
+            }
+        }
+
+        if let Some(expected) = &expect.body {
+            let actual = response.text();
+            if &actual != expected {
+                mismatches.push(Mismatch::Body {
+                    diff: TextDiff::from_lines(expected.as_str(), actual.as_str())
+                        .unified_diff()
+                        .header("expected", "actual")
+                        .to_string(),
+                });
+            }
+        }
+
+        if let Some(expected) = &expect.json {
+            let actual = response.json();
+            if !json_contains(&actual, expected) {
+                mismatches.push(Mismatch::Json {
+                    expected: expected.clone(),
+                    actual,
+                });
+            }
+        }
+
+        mismatches
+    }
+}
+
+impl RecordedResponse {
+    pub async fn record<B>(response: Response<B>) -> anyhow::Result<Self>
+    where
+        B: HttpBody,
+        B::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let (parts, body) = response.into_parts();
+        let body = body.collect().await?.to_bytes();
+
+        Ok(RecordedResponse {
+            status: parts.status,
+            headers: parts.headers,
+            body,
+        })
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    pub fn json(&self) -> Value {
+        serde_json::from_slice(&self.body).unwrap_or(Value::Null)
+    }
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Mismatch::Status { expected, actual } => {
+                write!(f, "status: expected {expected}, got {actual}")
+            }
+            Mismatch::Header {
+                name,
+                expected,
+                actual: Some(actual),
+            } => write!(f, "header {name}: expected {expected:?}, got {actual:?}"),
+            Mismatch::Header {
+                name,
+                expected,
+                actual: None,
+            } => write!(f, "header {name}: expected {expected:?}, got nothing"),
+            Mismatch::Body { diff } => write!(f, "body differs:\n{diff}"),
+            Mismatch::Json { expected, actual } => {
+                write!(f, "json: expected body to contain {expected}, got {actual}")
+            }
+        }
+    }
+}
+
+pub fn parse(contents: &str) -> anyhow::Result<Vec<Fixture>> {
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line).with_context(|| format!("line {}", index + 1))
+        })
+        .collect()
+}
+
+pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Vec<Fixture>> {
+    let path = path.as_ref();
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    parse(&contents).with_context(|| format!("parsing {}", path.display()))
+}
+
+fn json_contains(actual: &Value, expected: &Value) -> bool {
+    match (actual, expected) {
+        (Value::Object(actual), Value::Object(expected)) => expected.iter().all(|(key, value)| {
+            actual
+                .get(key)
+                .is_some_and(|actual| json_contains(actual, value))
+        }),
+        _ => actual == expected,
+    }
+}
@@ -0,0 +1,81 @@
+#[cfg(feature = "dec19")]
+use std::collections::HashMap;
+#[cfg(feature = "dec12")]
+use std::sync::PoisonError;
+
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+
+#[cfg(feature = "dec12")]
+use crate::challenge_dec12::MilkCookieGameSnapshot;
+use crate::{config::ServerConfig, AppState};
+
+const SNAPSHOT_ID: &str = "app_state";
+
+/// In-memory state of the compiled-in modules. Fields of modules left out of
+/// the build are skipped on save and ignored on restore.
+#[derive(Default, Deserialize, Serialize)]
+#[serde(default)]
+pub(crate) struct AppSnapshot {
+    #[cfg(feature = "dec9")]
+    pub milk_bucket_tokens: Option<u32>,
+    #[cfg(feature = "dec12")]
+    pub milk_cookie_game: Option<MilkCookieGameSnapshot>,
+    #[cfg(feature = "dec19")]
+    pub quote_pagination: HashMap<String, i32>,
+}
+
+impl AppState {
+    /// Builds the state from the last snapshot saved by [`AppState::persist`],
+    /// falling back to a fresh state when there is none.
+    pub async fn restore(pool: sqlx::PgPool, config: &ServerConfig) -> Result<Self, sqlx::Error> {
+        let snapshot = sqlx::query_scalar::<_, Json<AppSnapshot>>(
+            "SELECT state FROM app_snapshots WHERE id = $1",
+        )
+        .bind(SNAPSHOT_ID)
+        .fetch_optional(&pool)
+        .await?;
+
+        let Some(Json(snapshot)) = snapshot else {
+            return Ok(AppState::new(pool, config));
+        };
+
+        tracing::info!("restored in-memory state from snapshot");
+
+        Ok(AppState::from_snapshot(pool, config, snapshot))
+    }
+
+    /// Saves the in-memory game, milk bucket and pagination tokens to postgres.
+    /// Call it once no handler can still change them; a board poisoned by a
+    /// panicking handler is saved as it was left. The snapshot is written
+    /// through `pool` rather than the state's own, whose connections may
+    /// belong to a runtime that has already shut down.
+    pub async fn persist(&self, pool: &sqlx::PgPool) -> Result<(), sqlx::Error> {
+        let snapshot = AppSnapshot {
+            #[cfg(feature = "dec9")]
+            milk_bucket_tokens: Some(self.leaky_milk_bucket.tokens().await),
+            #[cfg(feature = "dec12")]
+            milk_cookie_game: Some(
+                self.milk_cookie_game
+                    .read()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .snapshot(),
+            ),
+            #[cfg(feature = "dec19")]
+            quote_pagination: self.quote_pagination.read().await.clone(),
+        };
+
+        sqlx::query(
+            "INSERT INTO app_snapshots (id, state, saved_at) VALUES ($1, $2, now())
+  ON CONFLICT (id) DO UPDATE SET state = EXCLUDED.state, saved_at = EXCLUDED.saved_at",
+        )
+        .bind(SNAPSHOT_ID)
+        .bind(Json(snapshot))
+        .execute(pool)
+        .await?;
+
+        tracing::info!("persisted in-memory state snapshot");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,68 @@
+use std::time::Duration;
+
+use axum::{
+    body::Body,
+    extract::MatchedPath,
+    http::{Request, Response},
+};
+use tracing::{field, Span};
+use tracing_subscriber::EnvFilter;
+
+use crate::{
+    config::{LogConfig, LogFormat},
+    request_id::REQUEST_ID_HEADER,
+};
+
+/// Installs the global subscriber for the standalone binary. Shuttle sets up
+/// its own subscriber, so the Shuttle entry point never calls this.
+pub fn init(config: &LogConfig) -> anyhow::Result<()> {
+    let filter = EnvFilter::try_new(&config.level)?;
+    let subscriber = tracing_subscriber::fmt().with_env_filter(filter);
+
+    match config.format {
+        LogFormat::Pretty => subscriber.pretty().try_init(),
+        LogFormat::Json => subscriber
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .with_span_list(false)
+            .try_init(),
+    }
+    .map_err(|e| anyhow::anyhow!(e))
+}
+
+pub fn make_request_span(request: &Request<Body>) -> Span {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(MatchedPath::as_str)
+        .unwrap_or("<unmatched>");
+    let request_id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+
+    tracing::info_span!(
+        "request",
+        method = %request.method(),
+        route,
+        request_id,
+        status = field::Empty,
+        latency_ms = field::Empty,
+    )
+}
+
+pub fn on_response(response: &Response<Body>, latency: Duration, span: &Span) {
+    let status = response.status().as_u16();
+    let latency_ms = latency.as_secs_f64() * 1000.0;
+
+    span.record("status", status);
+    span.record("latency_ms", latency_ms);
+
+    if response.status().is_server_error() {
+        tracing::error!(status, latency_ms, "finished request");
+    } else {
+        tracing::info!(status, latency_ms, "finished request");
+    }
+}
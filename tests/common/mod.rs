@@ -0,0 +1,92 @@
+#![allow(dead_code)]
+
+use std::{path::Path, sync::Arc};
+
+use axum::{body::Body, http::Request, Router};
+use shuttlings_cch24::{
+    config::ServerConfig,
+    replay::{self, Fixture, RecordedResponse},
+    router, AppState,
+};
+use sqlx::{postgres::PgPoolOptions, PgPool};
+use tower::ServiceExt;
+
+/// The router from `main.rs`, driven in-process through `oneshot`.
+pub struct TestApp {
+    router: Router,
+}
+
+impl TestApp {
+    /// App backed by a real pool, e.g. the ephemeral database handed out by
+    /// `#[sqlx::test]`.
+    pub fn new(pool: PgPool) -> Self {
+        TestApp::with_config(pool, &ServerConfig::default())
+    }
+
+    pub fn with_config(pool: PgPool, config: &ServerConfig) -> Self {
+        let app_state = Arc::new(AppState::new(pool, config));
+        TestApp {
+            router: router(app_state),
+        }
+    }
+
+    /// App whose pool never connects, for routes that do not touch postgres.
+    pub fn without_database() -> Self {
+        TestApp::new(lazy_pool())
+    }
+
+    pub fn without_database_with_config(config: &ServerConfig) -> Self {
+        TestApp::with_config(lazy_pool(), config)
+    }
+
+    pub async fn request(&self, request: Request<Body>) -> RecordedResponse {
+        let response = self.router.clone().oneshot(request).await.unwrap();
+        RecordedResponse::record(response).await.unwrap()
+    }
+
+    pub async fn get(&self, uri: &str) -> RecordedResponse {
+        self.request(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+    }
+
+    pub async fn post(
+        &self,
+        uri: &str,
+        content_type: &str,
+        body: impl Into<Body>,
+    ) -> RecordedResponse {
+        self.request(
+            Request::post(uri)
+                .header("content-type", content_type)
+                .body(body.into())
+                .unwrap(),
+        )
+        .await
+    }
+
+    pub async fn run_fixture(&self, fixture: &Fixture) {
+        let response = self.request(fixture.to_request("").unwrap()).await;
+
+        let mismatches = fixture.check(&response);
+        assert!(
+            mismatches.is_empty(),
+            "{}:\n{}",
+            fixture.name,
+            mismatches
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+    }
+}
+
+fn lazy_pool() -> PgPool {
+    PgPoolOptions::new()
+        .connect_lazy("postgres://cch24@localhost/cch24_unused")
+        .unwrap()
+}
+
+pub fn load_fixtures(path: impl AsRef<Path>) -> Vec<Fixture> {
+    replay::load(path).unwrap()
+}
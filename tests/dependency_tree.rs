@@ -0,0 +1,279 @@
+mod common;
+
+use std::{fs, path::PathBuf};
+
+use axum::http::StatusCode;
+use common::TestApp;
+use serde_json::{json, Value};
+use shuttlings_cch24::config::ServerConfig;
+
+const BOUNDARY: &str = "cch24-tree";
+
+const MANIFEST: &str = r#"[package]
+name = "workshop"
+version = "0.1.0"
+
+[dependencies]
+serde = { version = "1", features = ["derive"] }
+rand = "0.8"
+"#;
+
+/// Form with one field per `(name, content type, content)`.
+fn form(fields: &[(&str, &str, &str)]) -> String {
+    let mut body = String::new();
+    for (name, content_type, content) in fields {
+        body.push_str(&format!(
+            "--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"{name}\"\r\n\
+             Content-Type: {content_type}\r\n\r\n{content}\r\n"
+        ));
+    }
+    body.push_str(&format!("--{BOUNDARY}--\r\n"));
+    body
+}
+
+async fn tree(app: &TestApp, fields: &[(&str, &str, &str)]) -> (StatusCode, Value) {
+    let response = app
+        .post(
+            "/5/manifest/tree",
+            &format!("multipart/form-data; boundary={BOUNDARY}"),
+            form(fields),
+        )
+        .await;
+    (response.status, response.json())
+}
+
+fn find<'a>(nodes: &'a Value, name: &str) -> Vec<&'a Value> {
+    nodes
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|node| node["name"] == name)
+        .collect()
+}
+
+/// Writes a registry index snapshot with one line per `(crate, version json)`.
+fn index(name: &str, versions: &[(&str, Value)]) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("cch24-index-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    for (krate, version) in versions {
+        let path = match krate.len() {
+            1 => root.join("1").join(krate),
+            2 => root.join("2").join(krate),
+            3 => root.join("3").join(&krate[..1]).join(krate),
+            _ => root.join(&krate[..2]).join(&krate[2..4]).join(krate),
+        };
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let mut lines = fs::read_to_string(&path).unwrap_or_default();
+        lines.push_str(&format!("{version}\n"));
+        fs::write(&path, lines).unwrap();
+    }
+    root
+}
+
+#[tokio::test]
+async fn tree_follows_the_uploaded_lockfile() {
+    let app = TestApp::without_database();
+    let lockfile = r#"version = 3
+
+[[package]]
+name = "workshop"
+version = "0.1.0"
+dependencies = ["rand", "serde"]
+
+[[package]]
+name = "serde"
+version = "1.0.200"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = ["rand_core 0.5.1"]
+
+[[package]]
+name = "rand"
+version = "0.8.5"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = ["rand_core 0.6.4"]
+
+[[package]]
+name = "rand_core"
+version = "0.5.1"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "rand_core"
+version = "0.6.4"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+
+    let (status, tree) = tree(
+        &app,
+        &[
+            ("manifest", "application/toml", MANIFEST),
+            ("lockfile", "application/toml", lockfile),
+        ],
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(tree["root"], "workshop");
+    assert_eq!(tree["unresolved"], json!([]));
+
+    let serde = find(&tree["dependencies"], "serde")[0];
+    assert_eq!(serde["version"], "1.0.200");
+    assert_eq!(serde["features"], json!(["derive"]));
+    assert_eq!(serde["duplicate"], false);
+
+    let rand_core = find(&serde["dependencies"], "rand_core")[0];
+    assert_eq!(rand_core["version"], "0.5.1");
+    assert_eq!(rand_core["duplicate"], true);
+}
+
+#[tokio::test]
+async fn tree_resolves_through_the_index() {
+    let root = index(
+        "resolve",
+        &[
+            (
+                "serde",
+                json!({"name": "serde", "vers": "1.0.0", "deps": [
+                    {"name": "serde_derive", "req": "^1", "features": [], "optional": true, "default_features": true, "kind": "normal"},
+                    {"name": "trybuild", "req": "^1", "features": [], "optional": false, "default_features": true, "kind": "dev"}
+                ], "features": {"default": ["std"], "std": [], "derive": ["dep:serde_derive"]}, "yanked": false}),
+            ),
+            (
+                "serde",
+                json!({"name": "serde", "vers": "1.0.1", "deps": [], "features": {}, "yanked": true}),
+            ),
+            (
+                "serde_derive",
+                json!({"name": "serde_derive", "vers": "1.0.0", "deps": [], "features": {}, "yanked": false}),
+            ),
+        ],
+    );
+    let app = TestApp::without_database_with_config(&ServerConfig {
+        registry_index: Some(root),
+        ..ServerConfig::default()
+    });
+
+    let (status, tree) = tree(&app, &[("manifest", "application/toml", MANIFEST)]).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let serde = find(&tree["dependencies"], "serde")[0];
+    assert_eq!(serde["version"], "1.0.0");
+    assert_eq!(serde["yanked"], false);
+    assert_eq!(serde["features"], json!(["default", "derive", "std"]));
+    assert_eq!(
+        find(&serde["dependencies"], "serde_derive")[0]["version"],
+        "1.0.0"
+    );
+    assert!(find(&serde["dependencies"], "trybuild").is_empty());
+
+    assert_eq!(
+        tree["unresolved"],
+        json!([{"name": "rand", "requirement": "0.8", "required_by": "workshop", "reason": "not in the index"}])
+    );
+}
+
+#[tokio::test]
+async fn tree_marks_locked_yanked_versions() {
+    let root = index(
+        "yanked",
+        &[(
+            "serde",
+            json!({"name": "serde", "vers": "1.0.1", "deps": [], "features": {"derive": []}, "yanked": true}),
+        )],
+    );
+    let app = TestApp::without_database_with_config(&ServerConfig {
+        registry_index: Some(root),
+        ..ServerConfig::default()
+    });
+    let lockfile = "[[package]]\nname = \"serde\"\nversion = \"1.0.1\"\n\n\
+                    [[package]]\nname = \"rand\"\nversion = \"0.8.5\"\n";
+
+    let (status, tree) = tree(
+        &app,
+        &[
+            ("manifest", "application/toml", MANIFEST),
+            ("lockfile", "application/toml", lockfile),
+        ],
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(find(&tree["dependencies"], "serde")[0]["yanked"], true);
+    assert_eq!(find(&tree["dependencies"], "rand")[0]["yanked"], false);
+}
+
+#[tokio::test]
+async fn tree_needs_a_lockfile_or_an_index() {
+    let app = TestApp::without_database();
+
+    let (status, problem) = tree(&app, &[("manifest", "application/toml", MANIFEST)]).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(problem["code"], "manifest.no_resolution_source");
+}
+
+#[tokio::test]
+async fn tree_rejects_invalid_crate_names() {
+    let root = index("names", &[]);
+    let app = TestApp::without_database_with_config(&ServerConfig {
+        registry_index: Some(root),
+        ..ServerConfig::default()
+    });
+    let manifest = "[package]\nname = \"workshop\"\nversion = \"0.1.0\"\n\n\
+                    [dependencies]\n\"../../dev/zero\" = \"1\"\n\"sérde\" = \"1\"\n";
+
+    let (status, tree) = tree(&app, &[("manifest", "application/toml", manifest)]).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(tree["dependencies"], json!([]));
+    assert_eq!(
+        tree["unresolved"],
+        json!([
+            {"name": "../../dev/zero", "requirement": "1", "required_by": "workshop", "reason": "invalid crate name"},
+            {"name": "sérde", "requirement": "1", "required_by": "workshop", "reason": "invalid crate name"}
+        ])
+    );
+}
+
+#[tokio::test]
+async fn tree_survives_cyclic_feature_tables() {
+    let root = index(
+        "cyclic",
+        &[(
+            "serde",
+            json!({"name": "serde", "vers": "1.0.0", "deps": [], "features": {"default": ["a"], "a": ["b"], "b": ["a"]}, "yanked": false}),
+        )],
+    );
+    let app = TestApp::without_database_with_config(&ServerConfig {
+        registry_index: Some(root),
+        ..ServerConfig::default()
+    });
+    let manifest = "[package]\nname = \"workshop\"\nversion = \"0.1.0\"\n\n\
+                    [dependencies]\nserde = \"1\"\n";
+
+    let (status, tree) = tree(&app, &[("manifest", "application/toml", manifest)]).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        find(&tree["dependencies"], "serde")[0]["features"],
+        json!(["a", "b", "default"])
+    );
+}
+
+#[tokio::test]
+async fn tree_skips_optional_inherited_dependencies() {
+    let app = TestApp::without_database();
+    let manifest = "[package]\nname = \"workshop\"\nversion = \"0.1.0\"\n\n\
+                    [dependencies]\nserde = { workspace = true, optional = true }\n\
+                    rand = { workspace = true }\n\n\
+                    [workspace.dependencies]\nserde = \"1\"\nrand = \"0.8\"\n";
+    let lockfile = "[[package]]\nname = \"serde\"\nversion = \"1.0.200\"\n\n\
+                    [[package]]\nname = \"rand\"\nversion = \"0.8.5\"\n";
+
+    let (status, tree) = tree(
+        &app,
+        &[
+            ("manifest", "application/toml", manifest),
+            ("lockfile", "application/toml", lockfile),
+        ],
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(find(&tree["dependencies"], "serde").is_empty());
+    assert_eq!(find(&tree["dependencies"], "rand")[0]["version"], "0.8.5");
+}
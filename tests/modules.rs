@@ -0,0 +1,65 @@
+mod common;
+
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+};
+use common::TestApp;
+use serde_json::json;
+use shuttlings_cch24::{config::ServerConfig, modules::ChallengeModule};
+
+fn admin_config() -> ServerConfig {
+    ServerConfig {
+        disabled_modules: vec![ChallengeModule::Dec23],
+        admin_token: Some(String::from("let-me-in")),
+        ..ServerConfig::default()
+    }
+}
+
+fn toggle(module: &str, token: &str, enabled: bool) -> Request<Body> {
+    Request::put(format!("/admin/modules/{module}"))
+        .header(header::AUTHORIZATION, format!("Bearer {token}"))
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(json!({ "enabled": enabled }).to_string()))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn disabled_modules_answer_503_until_enabled() {
+    let app = TestApp::without_database_with_config(&admin_config());
+
+    let disabled = app.get("/23/star").await;
+    assert_eq!(disabled.status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(disabled.json()["code"], "module.disabled");
+
+    let enabled = app.request(toggle("dec23", "let-me-in", true)).await;
+    assert_eq!(enabled.status, StatusCode::OK);
+    assert_eq!(
+        enabled.json(),
+        json!({ "module": "dec23", "compiled": true, "enabled": true })
+    );
+    assert_eq!(app.get("/23/star").await.status, StatusCode::OK);
+
+    app.request(toggle("dec2", "let-me-in", false)).await;
+    let disabled = app.get("/2/dest?from=10.0.0.0&key=1.2.255.255").await;
+    assert_eq!(disabled.status, StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[tokio::test]
+async fn admin_needs_the_configured_token() {
+    let app = TestApp::without_database();
+    let response = app.request(toggle("dec2", "anything", false)).await;
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+
+    let app = TestApp::without_database_with_config(&admin_config());
+    let response = app.request(toggle("dec2", "wrong", false)).await;
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    let response = app.request(toggle("dec42", "let-me-in", false)).await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    assert_eq!(
+        app.get("/2/dest?from=10.0.0.0&key=1.2.255.255")
+            .await
+            .status,
+        StatusCode::OK
+    );
+}
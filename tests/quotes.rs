@@ -0,0 +1,95 @@
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use common::TestApp;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+async fn draft(app: &TestApp, author: &str, quote: &str) -> Value {
+    let response = app
+        .post(
+            "/19/draft",
+            "application/json",
+            json!({ "author": author, "quote": quote }).to_string(),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    response.json()
+}
+
+#[sqlx::test]
+async fn quote_lifecycle(pool: PgPool) {
+    let app = TestApp::new(pool);
+
+    let quote = draft(&app, "Santa", "Ho ho ho").await;
+    let id = quote["id"].as_str().unwrap();
+    assert_eq!(quote["version"], 1);
+
+    let cited = app.get(&format!("/19/cite/{id}")).await;
+    assert_eq!(cited.status, StatusCode::OK);
+    assert_eq!(cited.json(), quote);
+
+    let undone = app
+        .request(
+            Request::put(format!("/19/undo/{id}"))
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({ "author": "Santa", "quote": "Ho ho ho ho" }).to_string(),
+                ))
+                .unwrap(),
+        )
+        .await;
+    assert_eq!(undone.status, StatusCode::OK);
+    assert_eq!(undone.json()["version"], 2);
+    assert_eq!(undone.json()["quote"], "Ho ho ho ho");
+
+    let removed = app
+        .request(
+            Request::delete(format!("/19/remove/{id}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+    assert_eq!(removed.status, StatusCode::OK);
+
+    let missing = app.get(&format!("/19/cite/{id}")).await;
+    assert_eq!(missing.status, StatusCode::NOT_FOUND);
+    assert_eq!(missing.json()["code"], "quote.not_found");
+}
+
+#[sqlx::test]
+async fn quotes_are_paginated(pool: PgPool) {
+    let app = TestApp::new(pool);
+    for n in 1..=4 {
+        draft(&app, "Elf", &format!("quote {n}")).await;
+    }
+
+    let first = app.get("/19/list").await.json();
+    assert_eq!(first["page"], 1);
+    assert_eq!(first["quotes"].as_array().unwrap().len(), 3);
+    let token = first["next_token"].as_str().unwrap();
+
+    let second = app.get(&format!("/19/list?token={token}")).await.json();
+    assert_eq!(second["page"], 2);
+    assert_eq!(second["quotes"].as_array().unwrap().len(), 1);
+    assert_eq!(second["next_token"], Value::Null);
+
+    let bad_token = app.get("/19/list?token=0000000000000000").await;
+    assert_eq!(bad_token.status, StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test]
+async fn ready_once_migrated(pool: PgPool) {
+    let app = TestApp::new(pool);
+
+    let response = app.get("/readyz").await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(
+        response.json()["checks"]["migrations"]["pending"],
+        json!([])
+    );
+}
@@ -0,0 +1,232 @@
+mod common;
+
+use std::net::Ipv4Addr;
+
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+};
+use common::{load_fixtures, TestApp};
+use serde_json::json;
+use shuttlings_cch24::config::{MilkBucketConfig, ServerConfig};
+
+#[tokio::test]
+async fn routes_match_fixtures() {
+    // The milk fixtures count withdrawals, so the bucket must not refill
+    // while they run.
+    let app = TestApp::without_database_with_config(&ServerConfig {
+        milk_bucket: MilkBucketConfig {
+            refill_interval_secs: 24 * 60 * 60,
+            ..MilkBucketConfig::default()
+        },
+        ..ServerConfig::default()
+    });
+
+    for fixture in load_fixtures(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/routes.jsonl"
+    )) {
+        app.run_fixture(&fixture).await;
+    }
+}
+
+#[tokio::test]
+async fn wrapped_present_unwraps_from_cookie() {
+    let app = TestApp::without_database();
+    let present = json!({ "cookie": "chocolate chip", "quantity": 3 });
+
+    let wrapped = app
+        .post("/16/wrap", "application/json", present.to_string())
+        .await;
+    assert_eq!(wrapped.status, StatusCode::OK);
+    let cookie = wrapped.headers[header::SET_COOKIE].to_str().unwrap();
+
+    let unwrapped = app
+        .request(
+            Request::get("/16/unwrap")
+                .header(header::COOKIE, cookie)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+    assert_eq!(unwrapped.status, StatusCode::OK);
+    assert_eq!(unwrapped.json(), present);
+}
+
+#[tokio::test]
+async fn errors_carry_the_request_id() {
+    let app = TestApp::without_database();
+
+    let response = app
+        .request(
+            Request::get("/23/present/green")
+                .header("x-request-id", "fixture-request")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+
+    assert_eq!(response.headers["x-request-id"], "fixture-request");
+    assert_eq!(response.json()["request_id"], "fixture-request");
+}
+
+#[tokio::test]
+async fn batch_reports_bad_items_in_place() {
+    let app = TestApp::without_database();
+    let batch = json!([
+        { "op": "shift", "from": "10.0.0.0", "key": "1.2.3.4" },
+        { "op": "dest", "from": "10.0.0.0" },
+        { "op": "key", "from": "10.0.0.0", "to": "11.2.255.255" },
+    ]);
+
+    let response = app
+        .post("/2/batch", "application/json", batch.to_string())
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+
+    let results = response.json();
+    assert_eq!(results[0]["error"]["code"], "batch.invalid_item");
+    assert_eq!(results[1]["error"]["code"], "batch.invalid_item");
+    assert_eq!(results[2], json!({ "result": "1.2.255.255" }));
+}
+
+#[tokio::test]
+async fn stream_splits_lines_across_chunks() {
+    let app = TestApp::without_database();
+    let chunks = ["10.0.0.0,1.2", ".255.255\n10.0.", "0.0,0.0.0.1\n"]
+        .map(|chunk| Ok::<_, std::io::Error>(chunk.to_string()));
+
+    let response = app
+        .request(
+            Request::post("/2/stream?cipher=xor")
+                .body(Body::from_stream(futures_util::stream::iter(chunks)))
+                .unwrap(),
+        )
+        .await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(
+        response.text(),
+        "{\"result\":\"11.2.255.255\"}\n{\"result\":\"10.0.0.1\"}\n"
+    );
+}
+
+#[tokio::test]
+async fn stream_skips_overlong_lines() {
+    let app = TestApp::without_database();
+    let body = format!("{}\n10.0.0.0,0.0.0.1\n", "1".repeat(200));
+
+    let response = app.post("/2/stream", "text/plain", body).await;
+
+    let lines = response.text();
+    let lines = lines.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].contains("stream.line_too_long"));
+    assert_eq!(lines[1], "{\"result\":\"10.0.0.1\"}");
+}
+
+#[tokio::test]
+async fn anonymization_preserves_prefixes_and_reverses() {
+    let app = TestApp::without_database_with_config(&ServerConfig {
+        anonymization_secret: Some(String::from("shared between services")),
+        ..ServerConfig::default()
+    });
+
+    for address in ["10.1.2.3", "2001:db8::1", "::ffff:192.168.0.1"] {
+        let anonymized = app.get(&format!("/2/anonymize?address={address}")).await;
+        assert_eq!(anonymized.status, StatusCode::OK);
+        let anonymized = anonymized.text();
+        assert_ne!(anonymized, address);
+
+        let restored = app
+            .get(&format!("/2/deanonymize?address={anonymized}"))
+            .await;
+        assert_eq!(restored.text(), address);
+    }
+
+    let first = app.get("/2/anonymize?address=10.1.2.3").await.text();
+    let second = app.get("/2/anonymize?address=10.1.2.200").await.text();
+    let first = first.parse::<Ipv4Addr>().unwrap().octets();
+    let second = second.parse::<Ipv4Addr>().unwrap().octets();
+    assert_eq!(first[..3], second[..3]);
+}
+
+#[tokio::test]
+async fn anonymization_needs_a_secret() {
+    let app = TestApp::without_database();
+
+    let response = app.get("/2/anonymize?address=10.1.2.3").await;
+    assert_eq!(response.status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.json()["code"], "anonymize.not_configured");
+}
+
+#[tokio::test]
+async fn manifest_rules_come_from_the_config() {
+    let mut config = ServerConfig::default();
+    config.manifest_rules.required_keywords = vec![String::from("workshop")];
+    config.manifest_rules.allowed_licenses = vec![String::from("MIT"), String::from("Apache-2.0")];
+    config.manifest_rules.min_rust_version = Some("1.70".parse().unwrap());
+    config.manifest_rules.required_metadata_keys = vec![String::from("orders")];
+    let app = TestApp::without_database_with_config(&config);
+
+    let manifest = |rust_version: &str, license: &str| {
+        format!(
+            "[package]\nname = \"elves\"\nkeywords = [\"workshop\"]\n\
+             rust-version = \"{rust_version}\"\nlicense = \"{license}\"\n\n\
+             [[package.metadata.orders]]\nitem = \"Toy car\"\nquantity = 2\n"
+        )
+    };
+
+    let accepted = app
+        .post(
+            "/5/manifest",
+            "application/toml",
+            manifest("1.80", "MIT OR Apache-2.0"),
+        )
+        .await;
+    assert_eq!(accepted.status, StatusCode::OK);
+    assert_eq!(accepted.text(), "Toy car: 2");
+
+    let with_exception = app
+        .post(
+            "/5/manifest",
+            "application/toml",
+            manifest("2", "Apache-2.0 WITH LLVM-exception"),
+        )
+        .await;
+    assert_eq!(with_exception.status, StatusCode::OK);
+
+    let too_old = app
+        .post("/5/manifest", "application/toml", manifest("1.65", "MIT"))
+        .await;
+    assert_eq!(too_old.status, StatusCode::BAD_REQUEST);
+    assert_eq!(too_old.json()["code"], "manifest.rule.min_rust_version");
+
+    let wrong_license = app
+        .post(
+            "/5/manifest",
+            "application/toml",
+            manifest("1.80", "GPL-3.0"),
+        )
+        .await;
+    assert_eq!(
+        wrong_license.json()["code"],
+        "manifest.rule.allowed_licenses"
+    );
+    assert_eq!(
+        wrong_license.json()["detail"],
+        "Rule allowed_licenses failed: license \"GPL-3.0\" is not allowed"
+    );
+
+    let no_orders = app
+        .post(
+            "/5/manifest",
+            "application/toml",
+            "[package]\nname = \"elves\"\nkeywords = [\"workshop\"]\nlicense = \"MIT\"\n",
+        )
+        .await;
+    assert_eq!(
+        no_orders.json()["code"],
+        "manifest.rule.required_metadata_keys"
+    );
+}